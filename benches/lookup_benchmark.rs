@@ -0,0 +1,158 @@
+use std::{collections::HashSet, env};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use diesel::{connection::SimpleConnection, Connection, PgConnection, RunQueryDsl};
+use polyvinyl_acetate::{
+    fact_store::FactStore,
+    insert_orthotopes, ints_to_big_int,
+    models::{NewPair, NewPhrase},
+    ortho::Ortho,
+    ortho_to_orthotope,
+    schema::{pairs, phrases},
+    vec_of_words_to_big_int,
+    web_server::run_migrations,
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const VOCABULARY: i32 = 5_000;
+const ORTHOS: usize = 50_000;
+const PAIRS: usize = 100_000;
+const PHRASES: usize = 50_000;
+
+const INDEXES: &str = "orthotopes_hop_idx, orthotopes_contents_idx, orthotopes_origin_idx, \
+    pairs_first_word_idx, pairs_second_word_idx, phrases_phrase_head_idx, phrases_phrase_tail_idx";
+
+// Times the lookups the handlers make, against DATABASE_URL, with the indexes from
+// migrations/2026-10-18-180000_index_hot_lookups and again with them dropped. The rows are
+// seeded and the indexes dropped inside a transaction that is rolled back, but it holds locks
+// on the tables while it runs, so point it at a scratch database.
+pub fn lookup_benchmark(c: &mut Criterion) {
+    let database_url = match env::var("DATABASE_URL") {
+        Ok(url) => url,
+        Err(_) => {
+            eprintln!("DATABASE_URL is not set, skipping the lookup benchmarks");
+            return;
+        }
+    };
+    let conn = PgConnection::establish(&database_url).expect("database should be reachable");
+    run_migrations(&conn).expect("migrations should run");
+    conn.begin_test_transaction()
+        .expect("transaction should begin");
+    seed(&conn, &mut StdRng::seed_from_u64(0));
+    // Rows inserted in an open transaction stay in the GIN pending list, which every lookup
+    // scans in full until it is flushed.
+    conn.batch_execute(
+        "SELECT gin_clean_pending_list('orthotopes_hop_idx'), \
+            gin_clean_pending_list('orthotopes_contents_idx'); \
+         ANALYZE orthotopes, pairs, phrases",
+    )
+    .expect("indexes should be flushed and analyzed");
+
+    bench_lookups(c, &conn, "indexed");
+
+    conn.batch_execute(&format!(
+        "DROP INDEX {}; ANALYZE orthotopes, pairs, phrases",
+        INDEXES
+    ))
+    .expect("indexes should drop");
+
+    bench_lookups(c, &conn, "unindexed");
+}
+
+fn seed(conn: &PgConnection, rng: &mut StdRng) {
+    let mut word = || rng.gen_range(1..=VOCABULARY);
+
+    let mut orthos = HashSet::new();
+    while orthos.len() < ORTHOS {
+        let (a, b, c, d) = (word(), word(), word(), word());
+        if HashSet::from([a, b, c, d]).len() == 4 {
+            orthos.insert(ortho_to_orthotope(&Ortho::new(a, b, c, d)));
+        }
+    }
+    insert_orthotopes(conn, orthos).expect("orthos should insert");
+
+    let new_pairs: Vec<NewPair> = (0..PAIRS)
+        .map(|_| {
+            let (first_word, second_word) = (word(), word());
+            NewPair {
+                first_word,
+                second_word,
+                pair_hash: ints_to_big_int(first_word, second_word),
+            }
+        })
+        .collect();
+    for chunk in new_pairs.chunks(10_000) {
+        diesel::insert_into(pairs::table)
+            .values(chunk)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .expect("pairs should insert");
+    }
+
+    let new_phrases: Vec<NewPhrase> = (0..PHRASES)
+        .map(|i| {
+            let words: Vec<i32> = (0..3 + i % 3).map(|_| word()).collect();
+            NewPhrase {
+                words_hash: vec_of_words_to_big_int(words.clone()),
+                phrase_head: vec_of_words_to_big_int(words[..words.len() - 1].to_vec()),
+                phrase_tail: vec_of_words_to_big_int(words[1..].to_vec()),
+                words,
+            }
+        })
+        .collect();
+    for chunk in new_phrases.chunks(10_000) {
+        diesel::insert_into(phrases::table)
+            .values(chunk)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .expect("phrases should insert");
+    }
+}
+
+fn bench_lookups(c: &mut Criterion, conn: &PgConnection, label: &str) {
+    let words: Vec<i32> = (1..=10).collect();
+    let word_set: HashSet<i32> = words.iter().cloned().collect();
+    let hashes: HashSet<i64> = (0..10)
+        .map(|i| vec_of_words_to_big_int(vec![i, i + 1]))
+        .collect();
+
+    let mut group = c.benchmark_group(label);
+    group.sample_size(20);
+    group.bench_function("get_ortho_by_origin", |b| {
+        b.iter(|| conn.get_ortho_by_origin(1).unwrap())
+    });
+    group.bench_function("get_ortho_by_origin_batch", |b| {
+        b.iter(|| conn.get_ortho_by_origin_batch(word_set.clone()).unwrap())
+    });
+    group.bench_function("get_ortho_by_hop", |b| {
+        b.iter(|| conn.get_ortho_by_hop(words.clone()).unwrap())
+    });
+    group.bench_function("get_base_ortho_by_hop", |b| {
+        b.iter(|| conn.get_base_ortho_by_hop(words.clone()).unwrap())
+    });
+    group.bench_function("get_ortho_by_contents", |b| {
+        b.iter(|| conn.get_ortho_by_contents(words.clone()).unwrap())
+    });
+    group.bench_function("project_forward_batch", |b| {
+        b.iter(|| conn.project_forward_batch(word_set.clone()).unwrap())
+    });
+    group.bench_function("project_backward_batch", |b| {
+        b.iter(|| conn.project_backward_batch(word_set.clone()).unwrap())
+    });
+    group.bench_function("get_hashes_of_pairs_with_words_in", |b| {
+        b.iter(|| {
+            conn.get_hashes_of_pairs_with_words_in(word_set.clone(), word_set.clone())
+                .unwrap()
+        })
+    });
+    group.bench_function("phrase_exists_db_filter_head", |b| {
+        b.iter(|| conn.phrase_exists_db_filter_head(hashes.clone()).unwrap())
+    });
+    group.bench_function("phrase_exists_db_filter_tail", |b| {
+        b.iter(|| conn.phrase_exists_db_filter_tail(hashes.clone()).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, lookup_benchmark);
+criterion_main!(benches);
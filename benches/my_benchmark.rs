@@ -1,6 +1,9 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use maplit::btreemap;
-use polyvinyl_acetate::ortho::{Location, Ortho};
+use polyvinyl_acetate::{
+    ortho::{Location, Ortho},
+    ortho_encoding,
+};
 
 fn make_sample_ortho() -> Ortho {
     let abde = Ortho::new(1, 2, 4, 5);
@@ -211,6 +214,21 @@ pub fn phrase_head_summary_benchmark(c: &mut Criterion) {
     });
 }
 
+pub fn decode_benchmark(c: &mut Criterion) {
+    let bytes = ortho_encoding::encode(&make_sample_ortho());
+    c.bench_function("ortho decode", |b| {
+        b.iter(|| ortho_encoding::decode(&bytes))
+    });
+}
+
+// Rows written before orthos were dense hold one entry per location.
+pub fn decode_legacy_benchmark(c: &mut Criterion) {
+    let bytes = bincode::serialize(&make_sample_ortho().to_map()).unwrap();
+    c.bench_function("ortho decode legacy", |b| {
+        b.iter(|| ortho_encoding::decode(&bytes))
+    });
+}
+
 criterion_group!(
     benches,
     vocabulary_benchmark,
@@ -238,6 +256,8 @@ criterion_group!(
     all_full_length_phrases_benchmark,
     origin_phrases_benchmark,
     phrase_tail_summary_benchmark,
-    phrase_head_summary_benchmark
+    phrase_head_summary_benchmark,
+    decode_benchmark,
+    decode_legacy_benchmark
 );
 criterion_main!(benches);
@@ -19,11 +19,13 @@ depth = get("depth")
 sentences = get("sentences")
 pairs = get("pairs")
 phrases = get("phrases")
+collisions = get("collisions")
 print("depth:      " + str(depth))
 print("sentences:  " + str(sentences))
 print("count:      " + str(get("count")))
 print("pairs:      " + str(pairs))
 print("phrases:    " + str(phrases))
+print("collisions: " + str(collisions))
 print("1,1:        " + str(dims_11))
 print("1,1,1:      " + str(dims_111))
 print("1,1,1,1:    " + str(dims_1111))
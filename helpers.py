@@ -6,20 +6,71 @@ from helpers import *
 
 with open ("worker_node_ip.txt") as f:
     node_ip = f.read().strip()
-    
+
+class ApiError(Exception):
+    def __init__(self, body):
+        super().__init__(str(body["status"]) + " " + body["error"] + ": " + body["message"])
+        self.status = body["status"]
+        self.error = body["error"]
+        self.message = body["message"]
+
+# Every response body is JSON, and every error body has `status`, `error` and `message`.
+def request(req, data = None):
+    try:
+        body = r.urlopen(req, data).read().decode('utf-8')
+    except r.HTTPError as e:
+        raise ApiError(json.loads(e.read().decode('utf-8')))
+    return json.loads(body) if body else None
+
+def url(x, query = None):
+    return "http://" + node_ip + ":30001/" + x + ("?" + p.urlencode(query) if query else "")
+
 def get(x):
-    return int(r.urlopen("http://" + node_ip + ":30001/" + x).read().decode('utf-8'))
+    return request(url(x))["count"]
 
 def get_with_dims(dims):
-    return int(r.urlopen("http://" + node_ip + ":30001/orthos?dims=" + dims).read().decode('utf-8'))
+    return request(url("orthos", {"dims": dims}))["count"]
 
 def post(x, data):
-    req = r.Request("http://" + node_ip + ":30001/" + x)
+    req = r.Request(url(x))
     req.add_header('Content-Type', 'application/json')
-    return r.urlopen(req, data).read().decode('utf-8')
+    return request(req, data)
 
 def delete():
-    r.urlopen(r.Request(url = "http://" + node_ip + ":30001/", method = "DELETE"))
+    request(r.Request(url = url(""), method = "DELETE"))
+
+# Follows `next` from page to page, returning every item under `key`.
+def all_pages(x, key, query):
+    items = []
+    query = {k: v for k, v in query.items() if v is not None}
+    while True:
+        page = request(url(x, query))
+        items += page[key]
+        if page["next"] is None:
+            return items
+        query["after"] = page["next"]
+
+# Each ortho as a list of its full-length phrases, each a list of words.
+def splat_with_dims(dims, word=None, order=None, limit=None):
+    return all_pages("splat", "orthos", {"dims": dims, "word": word, "order": order, "limit": limit})
+
+# Every pair as its two words.
+def splat_pairs(word=None, order=None, limit=None):
+    return all_pages("splat-all-pairs", "pairs", {"word": word, "order": order, "limit": limit})
+
+# Each ortho drawn as a grid of words.
+def render_with_dims(dims, rows=None, columns=None):
+    query = {"dims": dims}
+    if rows is not None:
+        query["rows"] = rows
+    if columns is not None:
+        query["columns"] = columns
+    return request(url("render", query))["orthos"]
+
+# The orthos a word is in, under "origin", "hop" and "contents", each with its "dims" and "grid".
+def orthos_with_word(word):
+    return request(url("word-orthos", {"word": word}))
 
-def splat_with_dims(dims):
-    return r.urlopen("http://" + node_ip + ":30001/splat?dims=" + dims).read().decode('utf-8')
+# The orthos with the phrase along one of their axes, each with its "dims" and "grid".
+def orthos_with_phrase(phrase):
+    return request(url("phrase-orthos", {"phrase": phrase}))["orthos"]
@@ -0,0 +1,7 @@
+CREATE TABLE hash_collisions (
+    id SERIAL PRIMARY KEY,
+    table_name VARCHAR(64) NOT NULL,
+    hash BIGINT NOT NULL,
+    existing_id INTEGER NOT NULL,
+    attempted TEXT NOT NULL
+);
\ No newline at end of file
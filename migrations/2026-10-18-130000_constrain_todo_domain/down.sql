@@ -0,0 +1 @@
+ALTER TABLE todos DROP CONSTRAINT todos_domain_known;
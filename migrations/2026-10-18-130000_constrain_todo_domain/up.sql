@@ -0,0 +1,22 @@
+ALTER TABLE todos ADD CONSTRAINT todos_domain_known CHECK (domain IN (
+    'books',
+    'sentences',
+    'pairs',
+    'pair_up',
+    'ex_nihilo_ffbb',
+    'ex_nihilo_fbbf',
+    'up_by_origin',
+    'up_by_hop',
+    'up_by_contents',
+    'phrases',
+    'phrase_by_origin',
+    'phrase_by_hop',
+    'phrase_by_contents',
+    'orthotopes',
+    'ortho_up',
+    'ortho_up_forward',
+    'ortho_up_back',
+    'ortho_over',
+    'ortho_over_forward',
+    'ortho_over_back'
+));
\ No newline at end of file
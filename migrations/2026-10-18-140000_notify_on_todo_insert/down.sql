@@ -0,0 +1,2 @@
+DROP TRIGGER todos_inserted ON todos;
+DROP FUNCTION notify_todos_inserted();
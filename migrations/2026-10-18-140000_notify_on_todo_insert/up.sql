@@ -0,0 +1,11 @@
+CREATE FUNCTION notify_todos_inserted() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('todos_inserted', '');
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER todos_inserted
+    AFTER INSERT ON todos
+    FOR EACH STATEMENT
+    EXECUTE PROCEDURE notify_todos_inserted();
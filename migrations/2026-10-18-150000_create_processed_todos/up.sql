@@ -0,0 +1,3 @@
+CREATE TABLE processed_todos (
+    todo_id INTEGER PRIMARY KEY
+);
@@ -0,0 +1,6 @@
+CREATE TABLE todo_claims (
+    todo_id INTEGER PRIMARY KEY,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    available_at TIMESTAMP NOT NULL
+);
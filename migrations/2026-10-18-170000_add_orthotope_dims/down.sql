@@ -0,0 +1,2 @@
+DROP INDEX orthotopes_dims_idx;
+ALTER TABLE orthotopes DROP COLUMN dims, DROP COLUMN volume;
@@ -0,0 +1,54 @@
+ALTER TABLE orthotopes ADD COLUMN dims TEXT, ADD COLUMN volume INTEGER;
+
+-- Existing rows only have the bincode of the ortho: a u64 count of locations, each a u64 count of
+-- (i32 axis, u64 distance) entries followed by the i32 word. Counts and distances are small, so the
+-- low four bytes of each u64 are enough.
+CREATE FUNCTION pg_temp.read_u64(information BYTEA, at INTEGER) RETURNS INTEGER AS $$
+    SELECT get_byte(information, at)
+        + get_byte(information, at + 1) * 256
+        + get_byte(information, at + 2) * 65536
+        + get_byte(information, at + 3) * 16777216;
+$$ LANGUAGE SQL IMMUTABLE;
+
+-- The shape is the sorted distances of the farthest location, as in Ortho::get_shape.
+CREATE FUNCTION pg_temp.ortho_shape(information BYTEA) RETURNS TEXT AS $$
+DECLARE
+    pos INTEGER := 8;
+    axes INTEGER;
+    distance INTEGER;
+    total INTEGER;
+    farthest INTEGER := -1;
+    distances INTEGER[];
+    shape INTEGER[];
+BEGIN
+    FOR l IN 1..pg_temp.read_u64(information, 0) LOOP
+        axes := pg_temp.read_u64(information, pos);
+        pos := pos + 8;
+        distances := '{}';
+        total := 0;
+        FOR a IN 1..axes LOOP
+            distance := pg_temp.read_u64(information, pos + 4);
+            distances := distances || distance;
+            total := total + distance;
+            pos := pos + 12;
+        END LOOP;
+        pos := pos + 4;
+        IF total > farthest THEN
+            farthest := total;
+            shape := distances;
+        END IF;
+    END LOOP;
+    RETURN array_to_string(ARRAY(SELECT unnest(shape) ORDER BY 1), ',');
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+UPDATE orthotopes
+SET dims = pg_temp.ortho_shape(information),
+    volume = pg_temp.read_u64(information, 0);
+
+DROP FUNCTION pg_temp.ortho_shape(BYTEA);
+DROP FUNCTION pg_temp.read_u64(BYTEA, INTEGER);
+
+ALTER TABLE orthotopes ALTER COLUMN dims SET NOT NULL, ALTER COLUMN volume SET NOT NULL;
+
+CREATE INDEX orthotopes_dims_idx ON orthotopes (dims);
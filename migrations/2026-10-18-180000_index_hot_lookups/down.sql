@@ -0,0 +1,7 @@
+DROP INDEX phrases_phrase_tail_idx;
+DROP INDEX phrases_phrase_head_idx;
+DROP INDEX pairs_second_word_idx;
+DROP INDEX pairs_first_word_idx;
+DROP INDEX orthotopes_origin_idx;
+DROP INDEX orthotopes_contents_idx;
+DROP INDEX orthotopes_hop_idx;
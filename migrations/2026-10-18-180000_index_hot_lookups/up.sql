@@ -0,0 +1,9 @@
+-- The handlers look orthos up by hop and contents overlap (&&) and by origin, pairs by either
+-- word and phrases by head or tail hash, always with = ANY.
+CREATE INDEX orthotopes_hop_idx ON orthotopes USING GIN (hop);
+CREATE INDEX orthotopes_contents_idx ON orthotopes USING GIN (contents);
+CREATE INDEX orthotopes_origin_idx ON orthotopes (origin);
+CREATE INDEX pairs_first_word_idx ON pairs (first_word);
+CREATE INDEX pairs_second_word_idx ON pairs (second_word);
+CREATE INDEX phrases_phrase_head_idx ON phrases (phrase_head);
+CREATE INDEX phrases_phrase_tail_idx ON phrases (phrase_tail);
@@ -0,0 +1 @@
+ALTER TABLE processed_todos DROP COLUMN claimed_at
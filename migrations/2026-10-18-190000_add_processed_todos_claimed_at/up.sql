@@ -0,0 +1,2 @@
+ALTER TABLE processed_todos ADD COLUMN claimed_at TIMESTAMP NOT NULL DEFAULT now();
+CREATE INDEX processed_todos_claimed_at ON processed_todos (claimed_at);
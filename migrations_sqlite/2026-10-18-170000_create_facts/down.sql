@@ -0,0 +1,8 @@
+DROP TABLE ortho_contents;
+DROP TABLE ortho_hops;
+DROP TABLE orthotopes;
+DROP TABLE phrase_words;
+DROP TABLE phrases;
+DROP TABLE pairs;
+DROP TABLE sentences;
+DROP TABLE words;
@@ -0,0 +1,59 @@
+CREATE TABLE words (
+    id INTEGER PRIMARY KEY NOT NULL,
+    word TEXT NOT NULL,
+    word_hash BIGINT NOT NULL UNIQUE
+);
+
+CREATE TABLE sentences (
+    id INTEGER PRIMARY KEY NOT NULL,
+    sentence TEXT NOT NULL,
+    sentence_hash BIGINT NOT NULL UNIQUE
+);
+
+CREATE TABLE pairs (
+    id INTEGER PRIMARY KEY NOT NULL,
+    first_word INTEGER NOT NULL,
+    second_word INTEGER NOT NULL,
+    pair_hash BIGINT NOT NULL UNIQUE
+);
+CREATE INDEX pairs_first_word ON pairs (first_word);
+CREATE INDEX pairs_second_word ON pairs (second_word);
+
+CREATE TABLE phrases (
+    id INTEGER PRIMARY KEY NOT NULL,
+    phrase_head BIGINT NOT NULL,
+    phrase_tail BIGINT NOT NULL,
+    words_hash BIGINT NOT NULL UNIQUE
+);
+CREATE INDEX phrases_phrase_head ON phrases (phrase_head);
+CREATE INDEX phrases_phrase_tail ON phrases (phrase_tail);
+
+-- Replaces the words array of the Postgres phrases table.
+CREATE TABLE phrase_words (
+    phrase_id INTEGER NOT NULL REFERENCES phrases (id),
+    position INTEGER NOT NULL,
+    word INTEGER NOT NULL,
+    PRIMARY KEY (phrase_id, position)
+);
+
+CREATE TABLE orthotopes (
+    id INTEGER PRIMARY KEY NOT NULL,
+    information BLOB NOT NULL,
+    origin INTEGER NOT NULL,
+    base BOOLEAN NOT NULL,
+    info_hash BIGINT NOT NULL UNIQUE
+);
+CREATE INDEX orthotopes_origin ON orthotopes (origin);
+
+-- Replace the hop and contents arrays, so that overlap lookups are index joins.
+CREATE TABLE ortho_hops (
+    word INTEGER NOT NULL,
+    orthotope_id INTEGER NOT NULL REFERENCES orthotopes (id),
+    PRIMARY KEY (word, orthotope_id)
+);
+
+CREATE TABLE ortho_contents (
+    word INTEGER NOT NULL,
+    orthotope_id INTEGER NOT NULL REFERENCES orthotopes (id),
+    PRIMARY KEY (word, orthotope_id)
+);
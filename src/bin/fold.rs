@@ -0,0 +1,57 @@
+use std::{collections::BTreeMap, env, fs, io::Read};
+
+use polyvinyl_acetate::{fact_store::WritableFactStore, fold::Folder, ortho::Ortho};
+
+// Folds the files named on the command line (or stdin) in memory and prints how many orthos were
+// found for each shape, in the same dims notation as `/orthos?dims=`. With the `sqlite` feature,
+// `--sqlite <path>` folds into that file instead, adding to whatever it already holds.
+fn main() -> Result<(), anyhow::Error> {
+    let paths: Vec<String> = env::args().skip(1).collect();
+
+    #[cfg(feature = "sqlite")]
+    if let Some(flag) = paths.iter().position(|p| p == "--sqlite") {
+        use polyvinyl_acetate::sqlite_store;
+
+        let mut paths = paths;
+        paths.remove(flag);
+        anyhow::ensure!(flag < paths.len(), "--sqlite needs a database path");
+        let database = paths.remove(flag);
+        let conn = sqlite_store::establish_sqlite_connection(&database)?;
+        sqlite_store::run_sqlite_migrations(&conn)?;
+        let mut folder = Folder::with_store(conn);
+        add_texts(&mut folder, paths)?;
+        print_shapes(&sqlite_store::get_all_orthos(folder.store())?);
+        return Ok(());
+    }
+
+    let mut folder = Folder::new();
+    add_texts(&mut folder, paths)?;
+    print_shapes(folder.orthos());
+    Ok(())
+}
+
+fn add_texts<S: WritableFactStore>(
+    folder: &mut Folder<S>,
+    paths: Vec<String>,
+) -> Result<(), anyhow::Error> {
+    if paths.is_empty() {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        folder.add_text(&text)?;
+    }
+    for path in paths {
+        folder.add_text(&fs::read_to_string(path)?)?;
+    }
+    Ok(())
+}
+
+fn print_shapes(orthos: &[Ortho]) {
+    let mut by_shape: BTreeMap<String, usize> = BTreeMap::new();
+    for ortho in orthos {
+        *by_shape.entry(ortho.get_shape()).or_default() += 1;
+    }
+    for (shape, count) in by_shape {
+        println!("{shape}: {count}");
+    }
+    println!("total: {}", orthos.len());
+}
@@ -0,0 +1,24 @@
+use std::env;
+
+use polyvinyl_acetate::{establish_connection_safe, maintenance};
+
+fn main() -> Result<(), anyhow::Error> {
+    let command = env::args().nth(1).unwrap_or_default();
+    let conn = establish_connection_safe()?;
+    match command.as_str() {
+        "rehash" => {
+            for (table, changed) in maintenance::rehash_all(&conn)? {
+                println!("{table}: rehashed {changed} rows");
+            }
+        }
+        "reencode" => {
+            let changed = maintenance::reencode_orthotopes(&conn)?;
+            println!("orthotopes: reencoded {changed} rows");
+        }
+        other => {
+            println!("unknown command: {other:?}. expected one of: rehash, reencode");
+            std::process::exit(2);
+        }
+    }
+    Ok(())
+}
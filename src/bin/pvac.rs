@@ -0,0 +1,169 @@
+extern crate openssl;
+
+use std::{
+    env,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use amiquip::Connection;
+use diesel::{
+    r2d2::{ConnectionManager, Pool},
+    PgConnection,
+};
+use polyvinyl_acetate::{
+    establish_connection_safe,
+    in_memory_queue::InMemoryWorkQueue,
+    pg_queue::{self, WorkerConfig},
+    queue::{RabbitWorkQueue, RetryPolicy},
+    relay::{self, RelayConfig},
+    shutdown::Shutdown,
+    web_server,
+    worker_helper::{self, WorkerConcurrency},
+};
+
+// Runs the web server, the workers and (unless workers claim from Postgres) the relay in one
+// process. `PVAC_QUEUE` picks how todos reach the workers: `postgres` (default), `memory` or
+// `rabbitmq`. In `memory` the relay deletes todos from Postgres as it queues them in the process,
+// so whatever is still queued when the process stops, even on ctrl-c, is lost and never re-driven.
+// Rocket handles SIGTERM and ctrl-c; once it has stopped, the workers and relay finish what they
+// are doing and the process exits.
+#[rocket::main]
+async fn main() {
+    web_server::run_migrations(&establish_connection_safe().expect("cannot connect to the DB"))
+        .expect("migrations should run");
+
+    let workers: usize = env::var("PVAC_WORKERS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(4);
+    let retry_policy = RetryPolicy::from_env().expect("retry policy should parse");
+
+    let shutdown = Shutdown::default();
+
+    let tasks = match env::var("PVAC_QUEUE").as_deref().unwrap_or("postgres") {
+        "memory" => start_in_memory(workers, retry_policy, &shutdown),
+        "postgres" => start_postgres(workers, retry_policy, &shutdown),
+        "rabbitmq" => start_rabbitmq(workers, retry_policy, &shutdown),
+        other => panic!("unknown PVAC_QUEUE {}", other),
+    };
+
+    web_server::build()
+        .launch()
+        .await
+        .expect("web server should run");
+
+    shutdown.request();
+    for task in tasks {
+        task.join().expect("supervisor should not panic");
+    }
+}
+
+fn start_in_memory(
+    workers: usize,
+    retry_policy: RetryPolicy,
+    shutdown: &Shutdown,
+) -> Vec<JoinHandle<()>> {
+    let work_queue = InMemoryWorkQueue::new(retry_policy);
+    let pool = pool(workers);
+
+    let mut relay_queue = work_queue.clone();
+    let relay_shutdown = shutdown.clone();
+    let relay = supervise("relay".to_string(), shutdown, move || {
+        relay::relay_forever(&mut relay_queue, RelayConfig::from_env(), &relay_shutdown)
+    });
+    let mut worker_queue = work_queue;
+    let worker_shutdown = shutdown.clone();
+    let worker = supervise("worker".to_string(), shutdown, move || {
+        worker_helper::work(
+            &mut worker_queue,
+            pool.clone(),
+            WorkerConcurrency::new(workers),
+            &worker_shutdown,
+        )
+    });
+    vec![relay, worker]
+}
+
+fn start_postgres(
+    workers: usize,
+    retry_policy: RetryPolicy,
+    shutdown: &Shutdown,
+) -> Vec<JoinHandle<()>> {
+    let config = WorkerConfig {
+        retry_policy,
+        ..WorkerConfig::from_env().expect("worker config should parse")
+    };
+    (0..workers)
+        .map(|i| {
+            let worker_shutdown = shutdown.clone();
+            supervise(format!("worker {}", i), shutdown, move || {
+                pg_queue::claim_forever(config, &worker_shutdown)
+            })
+        })
+        .collect()
+}
+
+fn start_rabbitmq(
+    workers: usize,
+    retry_policy: RetryPolicy,
+    shutdown: &Shutdown,
+) -> Vec<JoinHandle<()>> {
+    let rabbit_url = env::var("RABBIT_URL").expect("RABBIT_URL must be set");
+    let pool = pool(workers);
+
+    let relay_url = rabbit_url.clone();
+    let relay_shutdown = shutdown.clone();
+    let relay = supervise("relay".to_string(), shutdown, move || {
+        let mut connection = Connection::insecure_open(&relay_url)?;
+        let channel = connection.open_channel(None)?;
+        let mut work_queue = RabbitWorkQueue::new(&channel, RetryPolicy::default())?;
+        relay::relay_forever(&mut work_queue, RelayConfig::from_env(), &relay_shutdown)?;
+        drop(work_queue);
+        connection.close()?;
+        Ok(())
+    });
+    let worker_shutdown = shutdown.clone();
+    let worker = supervise("worker".to_string(), shutdown, move || {
+        let concurrency = WorkerConcurrency::new(workers);
+        let mut connection = Connection::insecure_open(&rabbit_url)?;
+        let channel = connection.open_channel(None)?;
+        channel.qos(0, concurrency.prefetch, false)?;
+        let mut work_queue = RabbitWorkQueue::new(&channel, retry_policy)?;
+        worker_helper::work(&mut work_queue, pool.clone(), concurrency, &worker_shutdown)?;
+        drop(work_queue);
+        connection.close()?;
+        Ok(())
+    });
+    vec![relay, worker]
+}
+
+fn pool(workers: usize) -> Pool<ConnectionManager<PgConnection>> {
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let manager = ConnectionManager::<PgConnection>::new(&database_url);
+    Pool::builder()
+        .max_size(workers as u32)
+        .build(manager)
+        .expect("Failed to create pool.")
+}
+
+// Restarts the task after a failure, the way Kubernetes restarts a crashed pod, until shutdown is
+// requested.
+fn supervise<F>(name: String, shutdown: &Shutdown, mut task: F) -> JoinHandle<()>
+where
+    F: FnMut() -> Result<(), anyhow::Error> + Send + 'static,
+{
+    let shutdown = shutdown.clone();
+    thread::spawn(move || loop {
+        match task() {
+            Ok(()) => break,
+            Err(e) => {
+                println!("{} failed: {}", name, e);
+                if shutdown.requested() {
+                    break;
+                }
+                thread::sleep(Duration::from_secs(1));
+            }
+        }
+    })
+}
@@ -0,0 +1,32 @@
+use std::env;
+
+use polyvinyl_acetate::{
+    establish_connection_safe,
+    ortho_render::Layout,
+    web_helper::{parse_web_dims, render_orthos},
+};
+
+// Prints the orthos of one shape in DATABASE_URL as word grids, as `/render` does. Takes the dims
+// and optionally the rows and columns axes, e.g. `render 1,1,2` or `render 1,1,2 2 0`.
+fn main() -> Result<(), anyhow::Error> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (dims, layout) = match args.as_slice() {
+        [dims] => (dims, Layout::default()),
+        [dims, rows, columns] => (
+            dims,
+            Layout {
+                rows: rows.parse()?,
+                columns: columns.parse()?,
+            },
+        ),
+        _ => {
+            println!("usage: render <dims> [<rows> <columns>]");
+            std::process::exit(2);
+        }
+    };
+    println!(
+        "{}",
+        render_orthos(&establish_connection_safe()?, parse_web_dims(dims)?, layout)?.join("\n\n")
+    );
+    Ok(())
+}
@@ -1,41 +1,42 @@
-use amiquip::{
-    AmqpValue, Connection, ConsumerMessage, ConsumerOptions, FieldTable, QueueDeclareOptions,
-    Result,
-};
-use polyvinyl_acetate::models::Todo;
-use polyvinyl_acetate::worker_helper;
-use std::env;
+use amiquip::Connection;
+use polyvinyl_acetate::queue::{RabbitWorkQueue, RetryPolicy};
+use polyvinyl_acetate::pg_queue::{self, WorkerConfig};
+use polyvinyl_acetate::shutdown::{Shutdown, CLEAN_EXIT_CODE};
+use polyvinyl_acetate::worker_helper::{self, WorkerConcurrency};
+use std::{env, process};
 
 use opentelemetry::global;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use diesel::{r2d2::{ConnectionManager, Pool}, PgConnection};
 
 fn main() {
-    get().expect("Rabbit should not err");
+    let shutdown = Shutdown::register().expect("signal handlers should register");
+    match env::var("WORK_QUEUE_MODE").as_deref() {
+        Ok("postgres") => pg_queue::claim_forever(
+            WorkerConfig::from_env().expect("worker config should parse"),
+            &shutdown,
+        )
+        .expect("Postgres should not err"),
+        _ => get(&shutdown).expect("Rabbit should not err"),
+    }
+    if shutdown.requested() {
+        println!("shut down cleanly");
+        process::exit(CLEAN_EXIT_CODE);
+    }
 }
 
-fn get() -> Result<(), anyhow::Error> {
+fn get(shutdown: &Shutdown) -> Result<(), anyhow::Error> {
     let rabbit_url = env::var("RABBIT_URL").expect("RABBIT_URL must be set");
+    let retry_policy = RetryPolicy::from_env()?;
+    let concurrency = WorkerConcurrency::from_env()?;
 
     let mut connection = Connection::insecure_open(&rabbit_url)?;
 
     let channel = connection.open_channel(None)?;
 
-    let mut arguments = FieldTable::new();
-    arguments.insert("x-max-priority".to_string(), AmqpValue::ShortInt(20));
-
-    let queue = channel.queue_declare(
-        "work",
-        QueueDeclareOptions {
-            durable: true,
-            arguments,
-            ..QueueDeclareOptions::default()
-        },
-    )?;
+    channel.qos(0, concurrency.prefetch, false)?;
 
-    channel.qos(0, 1, false)?;
-
-    let consumer = queue.consume(ConsumerOptions::default())?;
+    let mut work_queue = RabbitWorkQueue::new(&channel, retry_policy)?;
 
     // global::set_text_map_propagator(opentelemetry_jaeger::Propagator::new());
     // let tracer = opentelemetry_jaeger::new_pipeline()
@@ -56,29 +57,15 @@ fn get() -> Result<(), anyhow::Error> {
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
     let manager = ConnectionManager::<PgConnection>::new(&database_url);
-    let pool = Pool::builder().max_size(1).build(manager).expect("Failed to create pool.");
+    let pool = Pool::builder()
+        .max_size(concurrency.pool_size)
+        .build(manager)
+        .expect("Failed to create pool.");
 
-    for (i, message) in consumer.receiver().iter().enumerate() {
-        println!("number of messages: {}", i);
-        match message {
-            ConsumerMessage::Delivery(delivery) => {
-                let todo: Todo = bincode::deserialize(&delivery.body)?;
-                println!("todo: {:?}", &todo);
-                match worker_helper::handle_todo(todo, pool.clone()) {
-                    Ok(_) => consumer.ack(delivery)?,
-                    Err(e) => {
-                        println!("requeuing because of {e}");
-                        consumer.nack(delivery, true)?
-                    }
-                }
-            }
-            other => {
-                println!("Consumer ended: {:?}", other);
-                break;
-            }
-        }
-    }
+    worker_helper::work(&mut work_queue, pool, concurrency, shutdown)?;
 
+    // Prefetched todos that were never handed out go back on the queue.
+    drop(work_queue);
     connection.close()?;
     Ok(())
 }
@@ -3,34 +3,31 @@ use std::collections::HashSet;
 use crate::models::{NewSentence, NewWords, Sentence, Todo};
 use crate::schema::books::{id, table as books};
 use crate::schema::words::{self};
+use crate::todo_domain::TodoDomain;
 use crate::{
-    create_todo_entry, schema, sentences, string_to_signed_int, Book,
-    NewTodo,
+    collisions, create_todo_entry, schema, sentences, string_to_signed_int, Book, NewTodo,
 };
 
-use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::dsl::any;
 use diesel::{ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl};
 use itertools::Itertools;
 
-#[tracing::instrument(level = "info", skip(pool))]
-pub fn handle_book_todo(todo: Todo, pool: Pool<ConnectionManager<PgConnection>>) -> Result<(), anyhow::Error> {
-    let conn = pool.get()?;
-    conn.build_transaction().serializable().run(|| {
-        let book = get_book(&conn, todo.other)?;
-        let new_vocabulary = split_book_to_words(&book);
-        insert_vocabulary(&conn, &new_vocabulary)?;
-        let new_sentences = split_book_to_sentences(book);
-        let sentences = insert_sentences(&conn, &new_sentences)?;
-        let todos: Vec<NewTodo> = sentences
-            .iter()
-            .map(|s| NewTodo {
-                domain: "sentences".to_owned(),
-                other: s.id,
-            })
-            .collect();
-        create_todo_entry(&conn, todos)?;
-        Ok(())
-    })
+#[tracing::instrument(level = "info", skip(conn))]
+pub fn handle_book_todo(todo: &Todo, conn: &PgConnection) -> Result<(), anyhow::Error> {
+    let book = get_book(conn, todo.other)?;
+    let new_vocabulary = split_book_to_words(&book);
+    insert_vocabulary(conn, &new_vocabulary)?;
+    let new_sentences = split_book_to_sentences(book);
+    let sentences = insert_sentences(conn, &new_sentences)?;
+    let todos: Vec<NewTodo> = sentences
+        .iter()
+        .map(|s| NewTodo {
+            domain: TodoDomain::Sentences,
+            other: s.id,
+        })
+        .collect();
+    create_todo_entry(conn, todos)?;
+    Ok(())
 }
 
 fn insert_vocabulary(
@@ -44,10 +41,32 @@ fn insert_vocabulary(
             word: s.clone(),
         })
         .collect();
-    diesel::insert_into(words::table)
-        .values(to_insert)
+    let mut existing: Vec<(i32, i64, String)> = diesel::insert_into(words::table)
+        .values(&to_insert)
         .on_conflict_do_nothing()
-        .execute(conn)
+        .returning((words::id, words::word_hash, words::word))
+        .get_results(conn)?;
+    let inserted = existing.len();
+
+    let attempted: Vec<(i64, String)> = to_insert
+        .into_iter()
+        .map(|w| (w.word_hash, w.word))
+        .collect();
+    let missed = collisions::missed_hashes(&attempted, &existing);
+    if !missed.is_empty() {
+        existing.extend(
+            words::table
+                .filter(words::word_hash.eq(any(missed)))
+                .select((words::id, words::word_hash, words::word))
+                .load::<(i32, i64, String)>(conn)?,
+        );
+    }
+    collisions::record_collisions(
+        conn,
+        collisions::find_collisions("words", attempted, existing),
+    )?;
+
+    Ok(inserted)
 }
 
 fn split_book_to_words(book: &Book) -> HashSet<String> {
@@ -71,12 +90,40 @@ fn split_book_to_words(book: &Book) -> HashSet<String> {
 
 fn insert_sentences(
     conn: &PgConnection,
-    sentences: &[NewSentence],
+    new_sentences: &[NewSentence],
 ) -> Result<Vec<Sentence>, diesel::result::Error> {
-    diesel::insert_into(sentences::table)
-        .values(sentences)
+    let inserted: Vec<Sentence> = diesel::insert_into(sentences::table)
+        .values(new_sentences)
         .on_conflict_do_nothing()
-        .get_results(conn)
+        .get_results(conn)?;
+
+    let attempted: Vec<(i64, String)> = new_sentences
+        .iter()
+        .map(|s| (s.sentence_hash, s.sentence.clone()))
+        .collect();
+    let mut existing: Vec<(i32, i64, String)> = inserted
+        .iter()
+        .map(|s| (s.id, s.sentence_hash, s.sentence.clone()))
+        .collect();
+    let missed = collisions::missed_hashes(&attempted, &existing);
+    if !missed.is_empty() {
+        existing.extend(
+            sentences::table
+                .filter(schema::sentences::sentence_hash.eq(any(missed)))
+                .select((
+                    schema::sentences::id,
+                    schema::sentences::sentence_hash,
+                    schema::sentences::sentence,
+                ))
+                .load::<(i32, i64, String)>(conn)?,
+        );
+    }
+    collisions::record_collisions(
+        conn,
+        collisions::find_collisions("sentences", attempted, existing),
+    )?;
+
+    Ok(inserted)
 }
 
 fn get_book(conn: &PgConnection, pk: i32) -> Result<Book, anyhow::Error> {
@@ -89,8 +136,17 @@ fn get_book(conn: &PgConnection, pk: i32) -> Result<Book, anyhow::Error> {
 }
 
 pub fn split_book_to_sentences(book: Book) -> Vec<NewSentence> {
-    book.body
-        .split_terminator(&['.', '!', '?', ';'])
+    split_text_to_sentences(&book.body)
+        .into_iter()
+        .map(|t| NewSentence {
+            sentence_hash: string_to_signed_int(&t),
+            sentence: t,
+        })
+        .collect()
+}
+
+pub(crate) fn split_text_to_sentences(text: &str) -> Vec<String> {
+    text.split_terminator(&['.', '!', '?', ';'])
         .filter(|x| !x.is_empty())
         .map(|x| x.trim())
         .map(|sentence| {
@@ -104,10 +160,6 @@ pub fn split_book_to_sentences(book: Book) -> Vec<NewSentence> {
                 })
                 .join(" ")
         })
-        .map(|t| NewSentence {
-            sentence: t.clone(),
-            sentence_hash: string_to_signed_int(&t),
-        })
         .collect()
 }
 
@@ -0,0 +1,136 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+};
+
+use diesel::{PgConnection, QueryDsl, RunQueryDsl};
+use itertools::Itertools;
+
+use crate::{models::NewHashCollision, schema::hash_collisions};
+
+// The hashes out of `attempted` that none of the `inserted` rows has. The insert left those rows
+// out, so the rows already holding the hashes have to be loaded to compare against.
+pub(crate) fn missed_hashes<C>(attempted: &[(i64, C)], inserted: &[(i32, i64, C)]) -> Vec<i64> {
+    let inserted_hashes: HashSet<i64> = inserted.iter().map(|(_, hash, _)| *hash).collect();
+    attempted
+        .iter()
+        .map(|(hash, _)| *hash)
+        .filter(|hash| !inserted_hashes.contains(hash))
+        .unique()
+        .collect()
+}
+
+// `attempted` is every row of an insert and `existing` every row now holding one of their hashes,
+// whether the insert just added it or it was there before. Two rows of one insert can share a
+// hash, in which case only one of them is inserted and the other must be checked against it.
+pub(crate) fn find_collisions<C: PartialEq + Debug>(
+    table_name: &str,
+    attempted: Vec<(i64, C)>,
+    existing: Vec<(i32, i64, C)>,
+) -> Vec<NewHashCollision> {
+    let existing_by_hash: HashMap<i64, (i32, C)> = existing
+        .into_iter()
+        .map(|(existing_id, hash, content)| (hash, (existing_id, content)))
+        .collect();
+
+    attempted
+        .into_iter()
+        .filter_map(|(hash, content)| {
+            let (existing_id, existing_content) = existing_by_hash.get(&hash)?;
+            if existing_content == &content {
+                None
+            } else {
+                Some(NewHashCollision {
+                    table_name: table_name.to_owned(),
+                    hash,
+                    existing_id: *existing_id,
+                    attempted: format!("{:?}", content),
+                })
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn record_collisions(
+    conn: &PgConnection,
+    collisions: Vec<NewHashCollision>,
+) -> Result<(), diesel::result::Error> {
+    if collisions.is_empty() {
+        return Ok(());
+    }
+
+    for collision in &collisions {
+        tracing::warn!(
+            "hash collision in {} on {}: existing row {} differs from {}",
+            collision.table_name,
+            collision.hash,
+            collision.existing_id,
+            collision.attempted
+        );
+    }
+
+    diesel::insert_into(hash_collisions::table)
+        .values(collisions)
+        .execute(conn)?;
+    Ok(())
+}
+
+pub fn count_collisions(conn: &PgConnection) -> Result<i64, diesel::result::Error> {
+    hash_collisions::table.count().get_result(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::collisions::{find_collisions, missed_hashes};
+    use crate::models::NewHashCollision;
+
+    #[test]
+    fn it_ignores_rows_that_were_already_present() {
+        let actual = find_collisions(
+            "words",
+            vec![(1, "a".to_owned()), (2, "b".to_owned())],
+            vec![(10, 1, "a".to_owned()), (11, 2, "b".to_owned())],
+        );
+        assert_eq!(actual, vec![]);
+    }
+
+    #[test]
+    fn it_finds_rows_whose_content_differs_under_the_same_hash() {
+        let actual = find_collisions(
+            "pairs",
+            vec![(1, (1, 2)), (2, (3, 4))],
+            vec![(10, 1, (1, 2)), (11, 2, (5, 6))],
+        );
+        assert_eq!(
+            actual,
+            vec![NewHashCollision {
+                table_name: "pairs".to_owned(),
+                hash: 2,
+                existing_id: 11,
+                attempted: "(3, 4)".to_owned()
+            }]
+        );
+    }
+
+    #[test]
+    fn it_finds_rows_of_one_insert_that_share_a_hash() {
+        // Both words hash to 7, so the insert only added "a".
+        let attempted = vec![
+            (7, "a".to_owned()),
+            (7, "b".to_owned()),
+            (8, "c".to_owned()),
+        ];
+        let inserted = vec![(10, 7, "a".to_owned()), (11, 8, "c".to_owned())];
+
+        assert!(missed_hashes(&attempted, &inserted).is_empty());
+        assert_eq!(
+            find_collisions("words", attempted, inserted),
+            vec![NewHashCollision {
+                table_name: "words".to_owned(),
+                hash: 7,
+                existing_id: 10,
+                attempted: "\"b\"".to_owned()
+            }]
+        );
+    }
+}
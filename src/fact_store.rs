@@ -0,0 +1,649 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use diesel::PgConnection;
+
+use crate::{
+    ints_to_big_int, ortho::Ortho, pair_todo_handler, vec_of_words_to_big_int, PairVocabulary, Word,
+};
+
+// The facts the derivation handlers read: orthos by origin, hop or contents, the pairs projected
+// from a word, and which phrases exist. `PgConnection` answers from the database inside the
+// caller's transaction. `InMemoryFactStore` answers from whatever has been added to it.
+pub trait FactStore {
+    fn get_ortho_by_origin(&self, origin: Word) -> Result<Vec<Ortho>, anyhow::Error>;
+
+    fn get_base_ortho_by_origin(&self, origin: Word) -> Result<Vec<Ortho>, anyhow::Error>;
+
+    fn get_ortho_by_origin_batch(
+        &self,
+        origins: HashSet<Word>,
+    ) -> Result<Vec<Ortho>, anyhow::Error>;
+
+    // Orthos whose hop shares at least one word with `hop`.
+    fn get_ortho_by_hop(&self, hop: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error>;
+
+    fn get_base_ortho_by_hop(&self, hop: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error>;
+
+    // Orthos whose contents share at least one word with `contents`.
+    fn get_ortho_by_contents(&self, contents: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error>;
+
+    fn get_base_ortho_by_contents(&self, contents: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error>;
+
+    // Squares a b / c d where `first` and `second` are a and b.
+    fn single_ffbb(&self, first: Word, second: Word) -> Result<Vec<Ortho>, anyhow::Error>;
+
+    // Squares a b / c d where `first` and `second` are b and d.
+    fn single_fbbf(&self, first: Word, second: Word) -> Result<Vec<Ortho>, anyhow::Error>;
+
+    fn project_forward(&self, from: Word) -> Result<HashSet<Word>, anyhow::Error>;
+
+    fn project_backward(&self, from: Word) -> Result<HashSet<Word>, anyhow::Error>;
+
+    fn project_forward_batch(
+        &self,
+        from: HashSet<Word>,
+    ) -> Result<HashSet<(Word, Word)>, anyhow::Error>;
+
+    fn project_backward_batch(
+        &self,
+        from: HashSet<Word>,
+    ) -> Result<HashSet<(Word, Word)>, anyhow::Error>;
+
+    // Hashes of the pairs whose first word is in `first_words` and second word in `second_words`.
+    fn get_hashes_of_pairs_with_words_in(
+        &self,
+        first_words: HashSet<Word>,
+        second_words: HashSet<Word>,
+    ) -> Result<HashSet<i64>, anyhow::Error>;
+
+    // The same pairs as `get_hashes_of_pairs_with_words_in`, split into their first words, second
+    // words and hashes.
+    fn get_hashes_and_words_of_pairs_with_words_in(
+        &self,
+        first_words: HashSet<Word>,
+        second_words: HashSet<Word>,
+    ) -> Result<PairVocabulary, anyhow::Error>;
+
+    // The words hashes out of `hashes` that belong to a known phrase.
+    fn get_phrases_with_matching_hashes(
+        &self,
+        hashes: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error>;
+
+    // Words hashes of the phrases whose head hash is in `heads` and tail hash is in `tails`.
+    fn phrase_exists_db_filter(
+        &self,
+        heads: HashSet<i64>,
+        tails: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error>;
+
+    fn phrase_exists_db_filter_head(
+        &self,
+        heads: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error>;
+
+    fn phrase_exists_db_filter_tail(
+        &self,
+        tails: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error>;
+}
+
+// A store that facts can be added to, which is all `fold::Folder` needs to fold into it. Like the
+// Postgres inserts, adding a fact that is already there does nothing, and each add returns
+// whether the fact was new.
+pub trait WritableFactStore: FactStore {
+    // The id of `word`, numbering it first if it is new.
+    fn add_word(&mut self, word: &str) -> Result<Word, anyhow::Error>;
+
+    fn add_sentence(&mut self, sentence: &str) -> Result<bool, anyhow::Error>;
+
+    fn add_pair(&mut self, first: Word, second: Word) -> Result<bool, anyhow::Error>;
+
+    fn add_phrase(&mut self, words: Vec<Word>) -> Result<bool, anyhow::Error>;
+
+    fn add_ortho(&mut self, ortho: Ortho) -> Result<bool, anyhow::Error>;
+}
+
+impl FactStore for PgConnection {
+    fn get_ortho_by_origin(&self, origin: Word) -> Result<Vec<Ortho>, anyhow::Error> {
+        crate::get_ortho_by_origin(self, origin)
+    }
+
+    fn get_base_ortho_by_origin(&self, origin: Word) -> Result<Vec<Ortho>, anyhow::Error> {
+        crate::get_base_ortho_by_origin(self, origin)
+    }
+
+    fn get_ortho_by_origin_batch(
+        &self,
+        origins: HashSet<Word>,
+    ) -> Result<Vec<Ortho>, anyhow::Error> {
+        crate::get_ortho_by_origin_batch(self, origins)
+    }
+
+    fn get_ortho_by_hop(&self, hop: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error> {
+        crate::get_ortho_by_hop(self, hop)
+    }
+
+    fn get_base_ortho_by_hop(&self, hop: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error> {
+        crate::get_base_ortho_by_hop(self, hop)
+    }
+
+    fn get_ortho_by_contents(&self, contents: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error> {
+        crate::get_ortho_by_contents(self, contents)
+    }
+
+    fn get_base_ortho_by_contents(&self, contents: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error> {
+        crate::get_base_ortho_by_contents(self, contents)
+    }
+
+    fn single_ffbb(&self, first: Word, second: Word) -> Result<Vec<Ortho>, anyhow::Error> {
+        pair_todo_handler::single_ffbb(self, first, second)
+    }
+
+    fn single_fbbf(&self, first: Word, second: Word) -> Result<Vec<Ortho>, anyhow::Error> {
+        pair_todo_handler::single_fbbf(self, first, second)
+    }
+
+    fn project_forward(&self, from: Word) -> Result<HashSet<Word>, anyhow::Error> {
+        crate::project_forward(self, from)
+    }
+
+    fn project_backward(&self, from: Word) -> Result<HashSet<Word>, anyhow::Error> {
+        crate::project_backward(self, from)
+    }
+
+    fn project_forward_batch(
+        &self,
+        from: HashSet<Word>,
+    ) -> Result<HashSet<(Word, Word)>, anyhow::Error> {
+        crate::project_forward_batch(self, from)
+    }
+
+    fn project_backward_batch(
+        &self,
+        from: HashSet<Word>,
+    ) -> Result<HashSet<(Word, Word)>, anyhow::Error> {
+        crate::project_backward_batch(self, from)
+    }
+
+    fn get_hashes_of_pairs_with_words_in(
+        &self,
+        first_words: HashSet<Word>,
+        second_words: HashSet<Word>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        crate::get_hashes_of_pairs_with_words_in(self, first_words, second_words)
+    }
+
+    fn get_hashes_and_words_of_pairs_with_words_in(
+        &self,
+        first_words: HashSet<Word>,
+        second_words: HashSet<Word>,
+    ) -> Result<PairVocabulary, anyhow::Error> {
+        crate::get_hashes_and_words_of_pairs_with_words_in(self, first_words, second_words)
+    }
+
+    fn get_phrases_with_matching_hashes(
+        &self,
+        hashes: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        crate::get_phrases_with_matching_hashes(self, hashes)
+    }
+
+    fn phrase_exists_db_filter(
+        &self,
+        heads: HashSet<i64>,
+        tails: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        crate::phrase_exists_db_filter(self, heads, tails)
+    }
+
+    fn phrase_exists_db_filter_head(
+        &self,
+        heads: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        crate::phrase_exists_db_filter_head(self, heads)
+    }
+
+    fn phrase_exists_db_filter_tail(
+        &self,
+        tails: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        crate::phrase_exists_db_filter_tail(self, tails)
+    }
+}
+
+// Holds pairs, phrases and orthos with the same uniqueness and lookups as the Postgres tables.
+// Orthos come back in the order they were added.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryFactStore {
+    vocabulary: HashMap<String, Word>,
+    sentences: HashSet<String>,
+    forward: HashMap<Word, HashSet<Word>>,
+    backward: HashMap<Word, HashSet<Word>>,
+    phrases: HashSet<i64>,
+    phrases_by_head: HashMap<i64, HashSet<i64>>,
+    phrases_by_tail: HashMap<i64, HashSet<i64>>,
+    orthos: Vec<Ortho>,
+    known_orthos: BTreeSet<Ortho>,
+    orthos_by_origin: HashMap<Word, Vec<usize>>,
+    orthos_by_hop: HashMap<Word, Vec<usize>>,
+    orthos_by_contents: HashMap<Word, Vec<usize>>,
+}
+
+impl InMemoryFactStore {
+    pub fn new() -> InMemoryFactStore {
+        InMemoryFactStore::default()
+    }
+
+    pub fn from_facts(
+        orthos: Vec<Ortho>,
+        pairs: Vec<(Word, Word)>,
+        phrases: Vec<Vec<Word>>,
+    ) -> InMemoryFactStore {
+        let mut store = InMemoryFactStore::new();
+        pairs.into_iter().for_each(|(f, s)| {
+            store.add_pair(f, s);
+        });
+        phrases.into_iter().for_each(|p| {
+            store.add_phrase(p);
+        });
+        orthos.into_iter().for_each(|o| {
+            store.add_ortho(o);
+        });
+        store
+    }
+
+    // Words are numbered from 1 in the order they are first added.
+    pub fn add_word(&mut self, word: &str) -> Word {
+        let next = self.vocabulary.len() as Word + 1;
+        *self.vocabulary.entry(word.to_string()).or_insert(next)
+    }
+
+    // Each of these returns whether the fact was new.
+    pub fn add_sentence(&mut self, sentence: &str) -> bool {
+        self.sentences.insert(sentence.to_string())
+    }
+
+    pub fn add_pair(&mut self, first: Word, second: Word) -> bool {
+        self.backward.entry(second).or_default().insert(first);
+        self.forward.entry(first).or_default().insert(second)
+    }
+
+    pub fn add_phrase(&mut self, words: Vec<Word>) -> bool {
+        let words_hash = vec_of_words_to_big_int(words.clone());
+        if !self.phrases.insert(words_hash) {
+            return false;
+        }
+        let head = vec_of_words_to_big_int(words[..words.len() - 1].to_vec());
+        let tail = vec_of_words_to_big_int(words[1..].to_vec());
+        self.phrases_by_head
+            .entry(head)
+            .or_default()
+            .insert(words_hash);
+        self.phrases_by_tail
+            .entry(tail)
+            .or_default()
+            .insert(words_hash);
+        true
+    }
+
+    pub fn add_ortho(&mut self, ortho: Ortho) -> bool {
+        if self.known_orthos.contains(&ortho) {
+            return false;
+        }
+        let index = self.orthos.len();
+        self.orthos_by_origin
+            .entry(ortho.get_origin())
+            .or_default()
+            .push(index);
+        for word in ortho.get_hop() {
+            self.orthos_by_hop.entry(word).or_default().push(index);
+        }
+        for word in ortho.get_contents() {
+            self.orthos_by_contents.entry(word).or_default().push(index);
+        }
+        self.known_orthos.insert(ortho.clone());
+        self.orthos.push(ortho);
+        true
+    }
+
+    pub fn vocabulary(&self) -> &HashMap<String, Word> {
+        &self.vocabulary
+    }
+
+    pub fn orthos(&self) -> &[Ortho] {
+        &self.orthos
+    }
+
+    fn lookup(
+        &self,
+        index: &HashMap<Word, Vec<usize>>,
+        words: impl IntoIterator<Item = Word>,
+        base_only: bool,
+    ) -> Vec<Ortho> {
+        let found: BTreeSet<usize> = words
+            .into_iter()
+            .flat_map(|w| index.get(&w).into_iter().flatten().copied())
+            .collect();
+        found
+            .into_iter()
+            .map(|i| &self.orthos[i])
+            .filter(|o| !base_only || o.is_base())
+            .cloned()
+            .collect()
+    }
+
+    fn has_pair(&self, first: Word, second: Word) -> bool {
+        self.forward
+            .get(&first)
+            .is_some_and(|seconds| seconds.contains(&second))
+    }
+
+    fn words_after(&self, word: Word) -> impl Iterator<Item = Word> + '_ {
+        self.forward.get(&word).into_iter().flatten().copied()
+    }
+
+    fn words_before(&self, word: Word) -> impl Iterator<Item = Word> + '_ {
+        self.backward.get(&word).into_iter().flatten().copied()
+    }
+
+    fn pairs_with_words_in(
+        &self,
+        first_words: &HashSet<Word>,
+        second_words: &HashSet<Word>,
+    ) -> Vec<(Word, Word)> {
+        first_words
+            .iter()
+            .flat_map(|f| {
+                self.forward
+                    .get(f)
+                    .into_iter()
+                    .flatten()
+                    .filter(|s| second_words.contains(s))
+                    .map(move |s| (*f, *s))
+            })
+            .collect()
+    }
+}
+
+impl FactStore for InMemoryFactStore {
+    fn get_ortho_by_origin(&self, origin: Word) -> Result<Vec<Ortho>, anyhow::Error> {
+        Ok(self.lookup(&self.orthos_by_origin, [origin], false))
+    }
+
+    fn get_base_ortho_by_origin(&self, origin: Word) -> Result<Vec<Ortho>, anyhow::Error> {
+        Ok(self.lookup(&self.orthos_by_origin, [origin], true))
+    }
+
+    fn get_ortho_by_origin_batch(
+        &self,
+        origins: HashSet<Word>,
+    ) -> Result<Vec<Ortho>, anyhow::Error> {
+        Ok(self.lookup(&self.orthos_by_origin, origins, false))
+    }
+
+    fn get_ortho_by_hop(&self, hop: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error> {
+        Ok(self.lookup(&self.orthos_by_hop, hop, false))
+    }
+
+    fn get_base_ortho_by_hop(&self, hop: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error> {
+        Ok(self.lookup(&self.orthos_by_hop, hop, true))
+    }
+
+    fn get_ortho_by_contents(&self, contents: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error> {
+        Ok(self.lookup(&self.orthos_by_contents, contents, false))
+    }
+
+    fn get_base_ortho_by_contents(&self, contents: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error> {
+        Ok(self.lookup(&self.orthos_by_contents, contents, true))
+    }
+
+    fn single_ffbb(&self, first: Word, second: Word) -> Result<Vec<Ortho>, anyhow::Error> {
+        Ok(self
+            .words_after(first)
+            .filter(|c| *c != second)
+            .flat_map(|c| {
+                self.words_after(second)
+                    .filter(move |d| self.has_pair(c, *d))
+                    .map(move |d| Ortho::new(first, second, c, d))
+            })
+            .collect())
+    }
+
+    fn single_fbbf(&self, first: Word, second: Word) -> Result<Vec<Ortho>, anyhow::Error> {
+        Ok(self
+            .words_before(second)
+            .filter(|c| *c != first)
+            .flat_map(|c| {
+                self.words_before(first)
+                    .filter(move |a| self.has_pair(*a, c))
+                    .map(move |a| Ortho::new(a, first, c, second))
+            })
+            .collect())
+    }
+
+    fn project_forward(&self, from: Word) -> Result<HashSet<Word>, anyhow::Error> {
+        Ok(self.forward.get(&from).cloned().unwrap_or_default())
+    }
+
+    fn project_backward(&self, from: Word) -> Result<HashSet<Word>, anyhow::Error> {
+        Ok(self.backward.get(&from).cloned().unwrap_or_default())
+    }
+
+    fn project_forward_batch(
+        &self,
+        from: HashSet<Word>,
+    ) -> Result<HashSet<(Word, Word)>, anyhow::Error> {
+        Ok(from
+            .into_iter()
+            .flat_map(|f| {
+                self.forward
+                    .get(&f)
+                    .into_iter()
+                    .flatten()
+                    .map(move |s| (f, *s))
+            })
+            .collect())
+    }
+
+    fn project_backward_batch(
+        &self,
+        from: HashSet<Word>,
+    ) -> Result<HashSet<(Word, Word)>, anyhow::Error> {
+        Ok(from
+            .into_iter()
+            .flat_map(|s| {
+                self.backward
+                    .get(&s)
+                    .into_iter()
+                    .flatten()
+                    .map(move |f| (*f, s))
+            })
+            .collect())
+    }
+
+    fn get_hashes_of_pairs_with_words_in(
+        &self,
+        first_words: HashSet<Word>,
+        second_words: HashSet<Word>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        Ok(self
+            .pairs_with_words_in(&first_words, &second_words)
+            .into_iter()
+            .map(|(f, s)| ints_to_big_int(f, s))
+            .collect())
+    }
+
+    fn get_hashes_and_words_of_pairs_with_words_in(
+        &self,
+        first_words: HashSet<Word>,
+        second_words: HashSet<Word>,
+    ) -> Result<PairVocabulary, anyhow::Error> {
+        let mut firsts = HashSet::new();
+        let mut seconds = HashSet::new();
+        let mut hashes = HashSet::new();
+        for (f, s) in self.pairs_with_words_in(&first_words, &second_words) {
+            firsts.insert(f);
+            seconds.insert(s);
+            hashes.insert(ints_to_big_int(f, s));
+        }
+        Ok((firsts, seconds, hashes))
+    }
+
+    fn get_phrases_with_matching_hashes(
+        &self,
+        hashes: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        Ok(hashes
+            .into_iter()
+            .filter(|h| self.phrases.contains(h))
+            .collect())
+    }
+
+    fn phrase_exists_db_filter(
+        &self,
+        heads: HashSet<i64>,
+        tails: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        let by_head = self.phrase_exists_db_filter_head(heads)?;
+        let by_tail = self.phrase_exists_db_filter_tail(tails)?;
+        Ok(by_head.intersection(&by_tail).copied().collect())
+    }
+
+    fn phrase_exists_db_filter_head(
+        &self,
+        heads: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        Ok(heads
+            .iter()
+            .flat_map(|h| self.phrases_by_head.get(h).into_iter().flatten().copied())
+            .collect())
+    }
+
+    fn phrase_exists_db_filter_tail(
+        &self,
+        tails: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        Ok(tails
+            .iter()
+            .flat_map(|t| self.phrases_by_tail.get(t).into_iter().flatten().copied())
+            .collect())
+    }
+}
+
+impl WritableFactStore for InMemoryFactStore {
+    fn add_word(&mut self, word: &str) -> Result<Word, anyhow::Error> {
+        Ok(InMemoryFactStore::add_word(self, word))
+    }
+
+    fn add_sentence(&mut self, sentence: &str) -> Result<bool, anyhow::Error> {
+        Ok(InMemoryFactStore::add_sentence(self, sentence))
+    }
+
+    fn add_pair(&mut self, first: Word, second: Word) -> Result<bool, anyhow::Error> {
+        Ok(InMemoryFactStore::add_pair(self, first, second))
+    }
+
+    fn add_phrase(&mut self, words: Vec<Word>) -> Result<bool, anyhow::Error> {
+        Ok(InMemoryFactStore::add_phrase(self, words))
+    }
+
+    fn add_ortho(&mut self, ortho: Ortho) -> Result<bool, anyhow::Error> {
+        Ok(InMemoryFactStore::add_ortho(self, ortho))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashset;
+
+    use crate::{
+        fact_store::{FactStore, InMemoryFactStore},
+        ints_to_big_int,
+        ortho::Ortho,
+        vec_of_words_to_big_int,
+    };
+
+    #[test]
+    fn it_looks_orthos_up_like_the_orthotopes_table() {
+        let abcd = Ortho::new(1, 2, 3, 4);
+        let bedf = Ortho::new(2, 5, 4, 6);
+        let mut store =
+            InMemoryFactStore::from_facts(vec![abcd.clone(), bedf.clone()], vec![], vec![]);
+        assert!(!store.add_ortho(abcd.clone()));
+
+        assert_eq!(store.get_ortho_by_origin(1).unwrap(), vec![abcd.clone()]);
+        assert_eq!(
+            store.get_ortho_by_origin_batch(hashset! {1, 2}).unwrap(),
+            vec![abcd.clone(), bedf.clone()]
+        );
+        assert_eq!(
+            store.get_ortho_by_hop(vec![4, 3]).unwrap(),
+            vec![abcd.clone(), bedf.clone()]
+        );
+        assert_eq!(store.get_ortho_by_contents(vec![6]).unwrap(), vec![bedf]);
+        assert_eq!(store.get_base_ortho_by_origin(1).unwrap(), vec![abcd]);
+        assert!(store.get_ortho_by_origin(7).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_projects_and_filters_pairs() {
+        let store = InMemoryFactStore::from_facts(vec![], vec![(1, 2), (1, 3), (4, 3)], vec![]);
+
+        assert_eq!(store.project_forward(1).unwrap(), hashset! {2, 3});
+        assert_eq!(store.project_backward(3).unwrap(), hashset! {1, 4});
+        assert_eq!(
+            store.project_backward_batch(hashset! {2, 3}).unwrap(),
+            hashset! {(1, 2), (1, 3), (4, 3)}
+        );
+        assert_eq!(
+            store
+                .get_hashes_and_words_of_pairs_with_words_in(hashset! {1}, hashset! {3, 4})
+                .unwrap(),
+            (hashset! {1}, hashset! {3}, hashset! {ints_to_big_int(1, 3)})
+        );
+    }
+
+    #[test]
+    fn it_finds_ex_nihilo_squares_like_the_pairs_join() {
+        // a b
+        // c d
+        let store =
+            InMemoryFactStore::from_facts(vec![], vec![(1, 2), (3, 4), (1, 3), (2, 4)], vec![]);
+
+        assert_eq!(
+            store.single_ffbb(1, 2).unwrap(),
+            vec![Ortho::new(1, 2, 3, 4)]
+        );
+        assert_eq!(
+            store.single_fbbf(2, 4).unwrap(),
+            vec![Ortho::new(1, 2, 3, 4)]
+        );
+        assert!(store.single_ffbb(3, 4).unwrap().is_empty());
+    }
+
+    #[test]
+    fn it_filters_phrases_by_head_and_tail() {
+        let store =
+            InMemoryFactStore::from_facts(vec![], vec![], vec![vec![1, 2, 5], vec![3, 4, 6]]);
+        let abe = vec_of_words_to_big_int(vec![1, 2, 5]);
+
+        assert_eq!(
+            store
+                .phrase_exists_db_filter(
+                    hashset! {vec_of_words_to_big_int(vec![1, 2])},
+                    hashset! {vec_of_words_to_big_int(vec![2, 5]), vec_of_words_to_big_int(vec![4, 6])},
+                )
+                .unwrap(),
+            hashset! {abe}
+        );
+        assert_eq!(
+            store
+                .get_phrases_with_matching_hashes(
+                    hashset! {abe, vec_of_words_to_big_int(vec![1, 2, 4])}
+                )
+                .unwrap(),
+            hashset! {abe}
+        );
+    }
+}
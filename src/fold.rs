@@ -0,0 +1,240 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use crate::{
+    book_todo_handler::split_text_to_sentences,
+    fact_store::{InMemoryFactStore, WritableFactStore},
+    ortho::Ortho,
+    over_on_ortho_found_handler, phrase_ortho_handler,
+    sentence_todo_handler::{split_sentence_to_pairs, split_sentence_to_phrases},
+    up_handler, up_on_ortho_found_handler, Word,
+};
+
+// A fact added to the store whose handler has not run yet, one per todo domain that the workers
+// fan out to.
+#[derive(Debug)]
+enum Pending {
+    Pair(Word, Word),
+    Phrase(Vec<Word>),
+    Ortho(Ortho),
+}
+
+// Folds texts the way the workers fold books, without a queue. Every new pair, phrase and ortho
+// runs the same handlers its todos would, against the store, until nothing new is found. Words
+// are numbered by the store, so ids differ from a Postgres run; compare through the vocabulary.
+#[derive(Debug, Default)]
+pub struct Folder<S = InMemoryFactStore> {
+    store: S,
+    pending: VecDeque<Pending>,
+}
+
+impl Folder {
+    pub fn new() -> Folder {
+        Folder::default()
+    }
+
+    pub fn vocabulary(&self) -> &HashMap<String, Word> {
+        self.store.vocabulary()
+    }
+
+    // In the order they were found.
+    pub fn orthos(&self) -> &[Ortho] {
+        self.store.orthos()
+    }
+}
+
+impl<S: WritableFactStore> Folder<S> {
+    pub fn with_store(store: S) -> Folder<S> {
+        Folder {
+            store,
+            pending: VecDeque::new(),
+        }
+    }
+
+    // Adds the text as a book and runs derivation to a fixpoint.
+    pub fn add_text(&mut self, text: &str) -> Result<(), anyhow::Error> {
+        for sentence in split_text_to_sentences(text) {
+            self.add_sentence(sentence)?;
+        }
+        while let Some(next) = self.pending.pop_front() {
+            let found = match next {
+                Pending::Pair(first, second) => self.pair_orthos(first, second)?,
+                Pending::Phrase(phrase) => self.phrase_orthos(phrase)?,
+                Pending::Ortho(ortho) => self.ortho_orthos(ortho)?,
+            };
+            for ortho in found {
+                if self.store.add_ortho(ortho.clone())? {
+                    self.pending.push_back(Pending::Ortho(ortho));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    fn add_sentence(&mut self, sentence: String) -> Result<(), anyhow::Error> {
+        if !self.store.add_sentence(&sentence)? {
+            return Ok(());
+        }
+        for word in sentence.split_ascii_whitespace() {
+            self.store.add_word(word)?;
+        }
+        for (first, second) in split_sentence_to_pairs(&sentence) {
+            let (first, second) = (self.store.add_word(&first)?, self.store.add_word(&second)?);
+            if self.store.add_pair(first, second)? {
+                self.pending.push_back(Pending::Pair(first, second));
+            }
+        }
+        for phrase in split_sentence_to_phrases(sentence) {
+            if phrase.len() <= 2 {
+                continue;
+            }
+            let phrase = phrase
+                .iter()
+                .map(|w| self.store.add_word(w))
+                .collect::<Result<Vec<Word>, _>>()?;
+            if self.store.add_phrase(phrase.clone())? {
+                self.pending.push_back(Pending::Phrase(phrase));
+            }
+        }
+        Ok(())
+    }
+
+    fn pair_orthos(&self, first: Word, second: Word) -> Result<Vec<Ortho>, anyhow::Error> {
+        let store = &self.store;
+        Ok([
+            store.single_ffbb(first, second)?,
+            store.single_fbbf(first, second)?,
+            up_handler::up_by_origin(store, first, second)?,
+            up_handler::up_by_hop(store, first, second)?,
+            up_handler::up_by_contents(store, first, second)?,
+        ]
+        .concat())
+    }
+
+    fn phrase_orthos(&self, phrase: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error> {
+        let store = &self.store;
+        Ok([
+            phrase_ortho_handler::over_by_origin(store, phrase.clone())?,
+            phrase_ortho_handler::over_by_hop(store, phrase.clone())?,
+            phrase_ortho_handler::over_by_contents(store, phrase)?,
+        ]
+        .concat())
+    }
+
+    fn ortho_orthos(&self, ortho: Ortho) -> Result<Vec<Ortho>, anyhow::Error> {
+        let store = &self.store;
+        Ok([
+            up_on_ortho_found_handler::up_forward(store, ortho.clone())?,
+            up_on_ortho_found_handler::up_back(store, ortho.clone())?,
+            over_on_ortho_found_handler::over_forward(store, ortho.clone())?,
+            over_on_ortho_found_handler::over_back(store, ortho)?,
+        ]
+        .concat())
+    }
+}
+
+// Folds a single text and returns every ortho found in it.
+pub fn fold(text: &str) -> Result<BTreeSet<Ortho>, anyhow::Error> {
+    let mut folder = Folder::new();
+    folder.add_text(text)?;
+    Ok(folder.orthos().iter().cloned().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use maplit::btreemap;
+
+    use crate::{
+        fold::{fold, Folder},
+        ortho::Ortho,
+    };
+
+    fn count_with_dims(folder: &Folder, dims: &[usize]) -> usize {
+        let mut wanted = btreemap! {};
+        for d in dims {
+            *wanted.entry(*d).or_insert(0) += 1;
+        }
+        folder
+            .orthos()
+            .iter()
+            .filter(|o| o.get_dims() == wanted)
+            .count()
+    }
+
+    #[test]
+    fn it_folds_a_square_from_four_pairs() {
+        let mut folder = Folder::new();
+        folder.add_text("a b. c d. a c. b d.").unwrap();
+        let word = |w: &str| folder.vocabulary()[w];
+
+        assert_eq!(
+            folder.orthos(),
+            &[Ortho::new(word("a"), word("b"), word("c"), word("d"))]
+        );
+    }
+
+    // The books from test.py, which the cluster folds to the same shapes.
+    #[test]
+    fn it_folds_up_across_books() {
+        let mut folder = Folder::new();
+        folder.add_text("a b c d. a c. b d. a b.").unwrap();
+        folder.add_text("e f. g h. e g. f h.").unwrap();
+        folder.add_text("b f. c g. d h. a e.").unwrap();
+
+        assert_eq!(count_with_dims(&folder, &[1, 1, 1]), 1);
+    }
+
+    #[test]
+    fn it_folds_over_by_contents() {
+        let mut folder = Folder::new();
+        folder.add_text("a b c. d e f. a d. b e. c f").unwrap();
+        folder.add_text("d e f. g h i. d g. e h. f i").unwrap();
+        folder.add_text("a d g. b e h. c f i").unwrap();
+
+        assert_eq!(count_with_dims(&folder, &[2, 2]), 1);
+    }
+
+    #[test]
+    fn it_folds_over_a_shared_phrase() {
+        let mut folder = Folder::new();
+        folder.add_text("a b e. c d f. a c. b d. e f.").unwrap();
+        let word = |w: &str| folder.vocabulary()[w];
+        let (a, b, c, d, e, f) = (
+            word("a"),
+            word("b"),
+            word("c"),
+            word("d"),
+            word("e"),
+            word("f"),
+        );
+
+        let abecdf = Ortho::zip_over(
+            &Ortho::new(a, b, c, d),
+            &Ortho::new(b, e, d, f),
+            &btreemap! {
+                e => b,
+                d => c
+            },
+            e,
+        );
+        assert!(folder.orthos().contains(&abecdf));
+    }
+
+    #[test]
+    fn folding_the_same_text_again_finds_nothing_new() {
+        let mut folder = Folder::new();
+        folder.add_text("a b. c d. a c. b d.").unwrap();
+        let before = folder.orthos().to_vec();
+        folder.add_text("a b. c d. a c. b d.").unwrap();
+        assert_eq!(folder.orthos(), &before[..]);
+
+        assert!(fold("").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    models::Todo,
+    queue::{Consumed, DeadLetter, Delivery, FailureAction, RetryPolicy, WorkQueue},
+};
+
+// A broker-free work queue for running the whole pipeline in one process. Clones share the same
+// queue. Todos are delivered highest domain priority first and in publish order within a
+// priority, like the RabbitMQ work queue. Retries are requeued immediately instead of after a
+// backoff, and nothing survives the process.
+#[derive(Clone)]
+pub struct InMemoryWorkQueue {
+    shared: Arc<Shared>,
+    policy: RetryPolicy,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    available: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    ready: BinaryHeap<Queued>,
+    next_sequence: u64,
+    dead_letters: Vec<DeadLetter>,
+    closed: bool,
+}
+
+struct Queued {
+    priority: u8,
+    sequence: u64,
+    todo: Todo,
+    attempts: u32,
+}
+
+impl Queued {
+    fn key(&self) -> (u8, Reverse<u64>) {
+        (self.priority, Reverse(self.sequence))
+    }
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Queued {}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl State {
+    fn push(&mut self, todo: Todo, attempts: u32) {
+        self.ready.push(Queued {
+            priority: todo.domain.priority(),
+            sequence: self.next_sequence,
+            todo,
+            attempts,
+        });
+        self.next_sequence += 1;
+    }
+}
+
+impl InMemoryWorkQueue {
+    pub fn new(policy: RetryPolicy) -> InMemoryWorkQueue {
+        InMemoryWorkQueue {
+            shared: Arc::new(Shared {
+                state: Mutex::new(State::default()),
+                available: Condvar::new(),
+            }),
+            policy,
+        }
+    }
+
+    // Consumers drain what is already queued and then get `None`.
+    pub fn close(&self) {
+        self.shared
+            .state
+            .lock()
+            .expect("queue lock poisoned")
+            .closed = true;
+        self.shared.available.notify_all();
+    }
+
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        let state = self.shared.state.lock().expect("queue lock poisoned");
+        state.dead_letters.clone()
+    }
+}
+
+impl Default for InMemoryWorkQueue {
+    fn default() -> Self {
+        InMemoryWorkQueue::new(RetryPolicy::default())
+    }
+}
+
+impl WorkQueue for InMemoryWorkQueue {
+    type Handle = u32;
+
+    fn publish(&mut self, todos: &[Todo]) -> Result<(), anyhow::Error> {
+        let mut state = self.shared.state.lock().expect("queue lock poisoned");
+        for todo in todos {
+            state.push(todo.clone(), 0);
+        }
+        self.shared.available.notify_all();
+        Ok(())
+    }
+
+    fn consume(&mut self) -> Result<Option<Delivery<Self::Handle>>, anyhow::Error> {
+        let mut state = self.shared.state.lock().expect("queue lock poisoned");
+        loop {
+            if let Some(queued) = state.ready.pop() {
+                return Ok(Some(Delivery {
+                    todo: queued.todo,
+                    handle: queued.attempts,
+                }));
+            }
+            if state.closed {
+                return Ok(None);
+            }
+            state = self
+                .shared
+                .available
+                .wait(state)
+                .expect("queue lock poisoned");
+        }
+    }
+
+    fn consume_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Consumed<Self::Handle>, anyhow::Error> {
+        let deadline = Instant::now() + timeout;
+        let mut state = self.shared.state.lock().expect("queue lock poisoned");
+        loop {
+            if let Some(queued) = state.ready.pop() {
+                return Ok(Consumed::Delivery(Delivery {
+                    todo: queued.todo,
+                    handle: queued.attempts,
+                }));
+            }
+            if state.closed {
+                return Ok(Consumed::Ended);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(Consumed::TimedOut);
+            }
+            state = self
+                .shared
+                .available
+                .wait_timeout(state, deadline - now)
+                .expect("queue lock poisoned")
+                .0;
+        }
+    }
+
+    fn ack(&mut self, _delivery: Delivery<Self::Handle>) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+
+    fn nack(
+        &mut self,
+        delivery: Delivery<Self::Handle>,
+        error: &str,
+    ) -> Result<FailureAction, anyhow::Error> {
+        let attempts = delivery.handle + 1;
+        let action = self.policy.on_failure(attempts);
+        let mut state = self.shared.state.lock().expect("queue lock poisoned");
+        match action {
+            FailureAction::Retry { .. } => {
+                state.push(delivery.todo, attempts);
+                self.shared.available.notify_one();
+            }
+            FailureAction::DeadLetter => state.dead_letters.push(DeadLetter {
+                todo: Some(delivery.todo),
+                attempts,
+                error: Some(error.to_owned()),
+            }),
+        }
+        Ok(action)
+    }
+
+    fn depth(&mut self) -> Result<u32, anyhow::Error> {
+        let state = self.shared.state.lock().expect("queue lock poisoned");
+        Ok(state.ready.len() as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use crate::{
+        in_memory_queue::InMemoryWorkQueue,
+        models::Todo,
+        queue::{Consumed, FailureAction, RetryPolicy, WorkQueue},
+        todo_domain::TodoDomain,
+    };
+
+    fn todo(id: i32, domain: TodoDomain) -> Todo {
+        Todo {
+            id,
+            domain,
+            other: id,
+        }
+    }
+
+    #[test]
+    fn it_delivers_by_priority_then_publish_order() {
+        let mut queue = InMemoryWorkQueue::default();
+        queue
+            .publish(&[
+                todo(1, TodoDomain::Books),
+                todo(2, TodoDomain::OrthoOver),
+                todo(3, TodoDomain::Books),
+                todo(4, TodoDomain::Pairs),
+            ])
+            .unwrap();
+        assert_eq!(queue.depth().unwrap(), 4);
+        queue.close();
+
+        let mut ids = vec![];
+        while let Some(delivery) = queue.consume().unwrap() {
+            ids.push(delivery.todo.id);
+            queue.ack(delivery).unwrap();
+        }
+        assert_eq!(ids, vec![2, 4, 1, 3]);
+        assert_eq!(queue.depth().unwrap(), 0);
+    }
+
+    #[test]
+    fn it_retries_and_then_dead_letters() {
+        let mut queue = InMemoryWorkQueue::new(RetryPolicy {
+            max_attempts: 2,
+            base_delay_ms: 10,
+        });
+        queue.publish(&[todo(1, TodoDomain::Books)]).unwrap();
+        queue.close();
+
+        let first = queue.consume().unwrap().unwrap();
+        assert_eq!(
+            queue.nack(first, "boom").unwrap(),
+            FailureAction::Retry { delay_ms: 10 }
+        );
+        let second = queue.consume().unwrap().unwrap();
+        assert_eq!(
+            queue.nack(second, "boom again").unwrap(),
+            FailureAction::DeadLetter
+        );
+        assert!(queue.consume().unwrap().is_none());
+
+        let dead = queue.dead_letters();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].todo, Some(todo(1, TodoDomain::Books)));
+        assert_eq!(dead[0].attempts, 2);
+        assert_eq!(dead[0].error, Some("boom again".to_owned()));
+    }
+
+    #[test]
+    fn consumers_block_until_something_is_published() {
+        let queue = InMemoryWorkQueue::default();
+        let mut consumer = queue.clone();
+        let handle = thread::spawn(move || consumer.consume().unwrap().map(|d| d.todo.id));
+
+        let mut publisher = queue.clone();
+        publisher.publish(&[todo(7, TodoDomain::Pairs)]).unwrap();
+        assert_eq!(handle.join().unwrap(), Some(7));
+    }
+
+    #[test]
+    fn consume_timeout_gives_up_when_nothing_is_published() {
+        let mut queue = InMemoryWorkQueue::default();
+        assert!(matches!(
+            queue.consume_timeout(Duration::from_millis(10)).unwrap(),
+            Consumed::TimedOut
+        ));
+
+        queue.publish(&[todo(3, TodoDomain::Books)]).unwrap();
+        queue.close();
+        match queue.consume_timeout(Duration::from_millis(10)).unwrap() {
+            Consumed::Delivery(delivery) => assert_eq!(delivery.todo.id, 3),
+            other => panic!("expected a delivery, got {:?}", other),
+        }
+        assert!(matches!(
+            queue.consume_timeout(Duration::from_millis(10)).unwrap(),
+            Consumed::Ended
+        ));
+    }
+}
@@ -3,7 +3,11 @@ pub mod schema;
 
 #[macro_use]
 extern crate diesel;
+#[macro_use]
+extern crate diesel_migrations;
 extern crate dotenv;
+#[macro_use]
+extern crate rocket;
 
 use diesel::dsl::any;
 use diesel::pg::PgConnection;
@@ -13,17 +17,36 @@ use diesel::prelude::*;
 use maplit::hashset;
 use schema::{phrases, sentences, todos};
 mod book_todo_handler;
+pub mod collisions;
+pub mod fact_store;
+pub mod fold;
+pub mod in_memory_queue;
+pub mod maintenance;
 pub mod ortho;
+pub mod ortho_encoding;
+pub mod ortho_render;
 mod ortho_todo_handler;
 pub mod over_on_ortho_found_handler;
 mod pair_todo_handler;
+pub mod pg_queue;
 pub mod phrase_ortho_handler;
 pub mod phrase_todo_handler;
+mod processed_todos;
+pub mod queue;
+pub mod relay;
 mod sentence_todo_handler;
+pub mod shutdown;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_schema;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
+pub mod stable_hasher;
+pub mod todo_domain;
 mod up_handler;
 mod up_helper;
 mod up_on_ortho_found_handler;
 pub mod web_helper;
+pub mod web_server;
 pub mod worker_helper;
 
 use crate::models::{NewOrthotope, Orthotope};
@@ -33,57 +56,15 @@ use crate::schema::pairs::table as pairs;
 use crate::{models::NewTodo, schema::books::dsl::books};
 use diesel::query_dsl::methods::SelectDsl;
 use models::Book;
+use stable_hasher::StableHasher;
 use std::collections::{HashMap, HashSet};
-use std::{
-    collections::hash_map::DefaultHasher,
-    env,
-    hash::{Hash, Hasher},
-};
-
-type FailableWordVecToOrthoVec =
-    fn(Option<&PgConnection>, Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error>;
-type FailableWordToOrthoVec = fn(Option<&PgConnection>, Word) -> Result<Vec<Ortho>, anyhow::Error>;
-
-type FailableHashsetWordsToHashsetNumbers = fn(
-    conn: Option<&PgConnection>,
-    first_words: HashSet<Word>,
-    second_words: HashSet<Word>,
-) -> Result<HashSet<i64>, anyhow::Error>;
-
-type FailableWordToVecOfOrthosfn = fn(
-    Option<&PgConnection>,
-    HashSet<Word>,
-) -> Result<Vec<Ortho>, anyhow::Error>;
-
-type FailableWordsetToWordset = fn(
-    Option<&PgConnection>,
-    HashSet<i64>,
-) -> Result<HashSet<i64>, anyhow::Error>;
-
-type FailableWordsetToWordTupleset = fn(
-    Option<&PgConnection>,
-    HashSet<Word>,
-) -> Result<HashSet<(Word, Word)>, anyhow::Error>;
-
-type FailableHashsetsWordsToHashsetWords = fn(
-    Option<&PgConnection>,
-    HashSet<i64>,
-    HashSet<i64>,
-) -> Result<HashSet<i64>, anyhow::Error>;
-
-type FailableWordsetsToTupleWordsets = fn(
-    Option<&PgConnection>,
-    HashSet<i32>,
-    HashSet<i32>,
-) -> Result<
-    (HashSet<Word>, HashSet<Word>, HashSet<i64>),
-    anyhow::Error,
->;
-
-type FailableWorsetsToTupleWordsetsResult = Result<(HashSet<Word>, HashSet<Word>, HashSet<i64>), anyhow::Error>;
+use std::env;
 
 type Word = i32;
 
+// The first words, second words and hashes of a set of pairs.
+type PairVocabulary = (HashSet<Word>, HashSet<Word>, HashSet<i64>);
+
 #[tracing::instrument(level = "info")]
 pub fn establish_connection_safe() -> Result<PgConnection, ConnectionError> {
     let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
@@ -92,7 +73,7 @@ pub fn establish_connection_safe() -> Result<PgConnection, ConnectionError> {
 
 #[tracing::instrument(level = "info", skip(conn))]
 pub fn get_hashes_of_pairs_with_words_in(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     first_words: HashSet<Word>,
     second_words: HashSet<Word>,
 ) -> Result<HashSet<i64>, anyhow::Error> {
@@ -103,7 +84,7 @@ pub fn get_hashes_of_pairs_with_words_in(
         ),
         crate::schema::pairs::pair_hash,
     )
-    .load(conn.expect("do not pass a test dummy in production"))?
+    .load(conn)?
     .iter()
     .cloned()
     .collect();
@@ -115,7 +96,7 @@ pub fn get_hashes_of_pairs_with_words_in(
         ),
         crate::schema::pairs::pair_hash,
     )
-    .load(conn.expect("do not pass a test dummy in production"))?
+    .load(conn)?
     .iter()
     .cloned()
     .collect();
@@ -125,10 +106,10 @@ pub fn get_hashes_of_pairs_with_words_in(
 
 #[tracing::instrument(level = "info", skip(conn))]
 pub fn get_hashes_and_words_of_pairs_with_words_in(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     first_words: HashSet<Word>,
     second_words: HashSet<Word>,
-) ->  FailableWorsetsToTupleWordsetsResult{
+) -> Result<PairVocabulary, anyhow::Error> {
     let firsts: HashSet<(Word, Word, i64)> = diesel::QueryDsl::select(
         diesel::QueryDsl::filter(
             pairs,
@@ -140,7 +121,7 @@ pub fn get_hashes_and_words_of_pairs_with_words_in(
             crate::schema::pairs::pair_hash,
         ),
     )
-    .load(conn.expect("do not pass a test dummy in production"))?
+    .load(conn)?
     .iter()
     .cloned()
     .collect();
@@ -156,7 +137,7 @@ pub fn get_hashes_and_words_of_pairs_with_words_in(
             crate::schema::pairs::pair_hash,
         ),
     )
-    .load(conn.expect("do not pass a test dummy in production"))?
+    .load(conn)?
     .iter()
     .cloned()
     .collect();
@@ -174,7 +155,7 @@ pub fn get_hashes_and_words_of_pairs_with_words_in(
 
 #[tracing::instrument(level = "info", skip(conn))]
 pub fn get_phrases_with_matching_hashes(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     all_phrases: HashSet<i64>,
 ) -> Result<HashSet<i64>, anyhow::Error> {
     use crate::phrases::dsl::phrases;
@@ -185,7 +166,7 @@ pub fn get_phrases_with_matching_hashes(
         ),
         crate::schema::phrases::words_hash,
     )
-    .load(conn.expect("do not pass a test dummy in production"))?
+    .load(conn)?
     .iter()
     .cloned()
     .collect();
@@ -195,7 +176,7 @@ pub fn get_phrases_with_matching_hashes(
 
 #[tracing::instrument(level = "info", skip(conn))]
 fn project_forward_batch(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     from: HashSet<Word>,
 ) -> Result<HashSet<(Word, Word)>, anyhow::Error> {
     let seconds_vec: Vec<(Word, Word)> = diesel::QueryDsl::select(
@@ -208,7 +189,7 @@ fn project_forward_batch(
             crate::schema::pairs::second_word,
         ),
     )
-    .load(conn.expect("do not pass a test dummy in production"))?;
+    .load(conn)?;
 
     let seconds = HashSet::from_iter(seconds_vec);
     Ok(seconds)
@@ -216,7 +197,7 @@ fn project_forward_batch(
 
 #[tracing::instrument(level = "info", skip(conn))]
 fn project_backward_batch(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     from: HashSet<Word>,
 ) -> Result<HashSet<(Word, Word)>, anyhow::Error> {
     let firsts_vec: Vec<(Word, Word)> = RunQueryDsl::load(
@@ -230,7 +211,7 @@ fn project_backward_batch(
                 crate::schema::pairs::second_word,
             ),
         ),
-        conn.expect("do not pass a test dummy in production"),
+        conn,
     )?;
 
     let firsts = HashSet::from_iter(firsts_vec);
@@ -261,47 +242,49 @@ fn create_todo_entry(
 }
 
 pub fn string_to_signed_int(t: &str) -> i64 {
-    let mut hasher = DefaultHasher::new();
-    t.hash(&mut hasher);
-    hasher.finish() as i64
+    let mut hasher = StableHasher::new();
+    hasher.write_bytes(t.as_bytes());
+    hasher.finish()
 }
 
 pub fn vec_of_words_to_big_int(v: Vec<Word>) -> i64 {
-    let mut hasher = DefaultHasher::new();
-    v.hash(&mut hasher);
-    hasher.finish() as i64
+    let mut hasher = StableHasher::new();
+    hasher.write_len(v.len());
+    v.into_iter().for_each(|w| hasher.write_i32(w));
+    hasher.finish()
 }
 
 pub fn vec_of_big_ints_to_big_int(v: Vec<i64>) -> i64 {
-    let mut hasher = DefaultHasher::new();
-    v.hash(&mut hasher);
-    hasher.finish() as i64
+    let mut hasher = StableHasher::new();
+    hasher.write_len(v.len());
+    v.into_iter().for_each(|i| hasher.write_i64(i));
+    hasher.finish()
 }
 
 pub fn string_refs_to_signed_int(l: &str, r: &str) -> i64 {
-    let mut hasher = DefaultHasher::new();
-    l.hash(&mut hasher);
-    r.hash(&mut hasher);
-    hasher.finish() as i64
+    let mut hasher = StableHasher::new();
+    hasher.write_bytes(l.as_bytes());
+    hasher.write_bytes(r.as_bytes());
+    hasher.finish()
 }
 
 pub fn ints_to_big_int(l: Word, r: Word) -> i64 {
-    let mut hasher = DefaultHasher::new();
-    l.hash(&mut hasher);
-    r.hash(&mut hasher);
-    hasher.finish() as i64
+    let mut hasher = StableHasher::new();
+    hasher.write_i32(l);
+    hasher.write_i32(r);
+    hasher.finish()
 }
 
 #[tracing::instrument(level = "info", skip(conn))]
 fn project_forward(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     from: Word,
 ) -> Result<HashSet<Word>, anyhow::Error> {
     let seconds_vec: Vec<Word> = diesel::QueryDsl::select(
         diesel::QueryDsl::filter(pairs, schema::pairs::first_word.eq(from)),
         crate::schema::pairs::second_word,
     )
-    .load(conn.expect("do not pass a test dummy in production"))?;
+    .load(conn)?;
 
     let seconds = HashSet::from_iter(seconds_vec);
     Ok(seconds)
@@ -309,7 +292,7 @@ fn project_forward(
 
 #[tracing::instrument(level = "info", skip(conn))]
 fn project_backward(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     from: Word,
 ) -> Result<HashSet<Word>, anyhow::Error> {
     let firsts_vec: Vec<Word> = RunQueryDsl::load(
@@ -317,7 +300,7 @@ fn project_backward(
             QueryDsl::filter(pairs, schema::pairs::second_word.eq(from)),
             crate::schema::pairs::first_word,
         ),
-        conn.expect("do not pass a test dummy in production"),
+        conn,
     )?;
 
     let firsts = HashSet::from_iter(firsts_vec);
@@ -337,9 +320,10 @@ pub fn insert_orthotopes(
     let mut res = vec![];
     for chunk in to_insert {
         let chunk_res: Vec<Orthotope> = diesel::insert_into(orthotopes::table)
-            .values(chunk)
+            .values(&chunk)
             .on_conflict_do_nothing()
             .get_results(conn)?;
+        check_orthotope_collisions(conn, chunk, &chunk_res)?;
         res.push(chunk_res);
     }
     let final_res = res.into_iter().flatten().collect();
@@ -347,9 +331,44 @@ pub fn insert_orthotopes(
     Ok(final_res)
 }
 
+fn check_orthotope_collisions(
+    conn: &PgConnection,
+    attempted: Vec<NewOrthotope>,
+    inserted: &[Orthotope],
+) -> Result<(), diesel::result::Error> {
+    use crate::schema::orthotopes::{id, info_hash, information, table as orthotopes};
+    // Compared decoded, since an older row holds the same ortho in different bytes.
+    let attempted: Vec<(i64, Option<Ortho>)> = attempted
+        .into_iter()
+        .map(|o| (o.info_hash, ortho_encoding::decode(&o.information).ok()))
+        .collect();
+    let mut existing: Vec<(i32, i64, Option<Ortho>)> = inserted
+        .iter()
+        .map(|o| (o.id, o.info_hash, ortho_encoding::decode(&o.information).ok()))
+        .collect();
+    let missed = collisions::missed_hashes(&attempted, &existing);
+    if !missed.is_empty() {
+        let loaded: Vec<(i32, i64, Vec<u8>)> = SelectDsl::select(
+            orthotopes.filter(info_hash.eq(any(missed))),
+            (id, info_hash, information),
+        )
+        .load(conn)?;
+        existing.extend(
+            loaded
+                .into_iter()
+                .map(|(pk, hash, info)| (pk, hash, ortho_encoding::decode(&info).ok())),
+        );
+    }
+
+    collisions::record_collisions(
+        conn,
+        collisions::find_collisions("orthotopes", attempted, existing),
+    )
+}
+
 #[tracing::instrument(level = "info", skip(conn))]
 pub fn get_ortho_by_origin(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     o: Word,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
     use crate::schema::orthotopes::{origin, table as orthotopes};
@@ -358,19 +377,16 @@ pub fn get_ortho_by_origin(
         FilterDsl::filter(orthotopes, origin.eq(o)),
         schema::orthotopes::information,
     )
-    .load(conn.expect("don't use test connections in production"))?;
+    .load(conn)?;
 
-    let res: Vec<Ortho> = results
-        .iter()
-        .map(|x| bincode::deserialize(x).expect("deserialization should succeed"))
-        .collect();
+    let res = ortho_encoding::decode_all(&results)?;
 
     Ok(res)
 }
 
 #[tracing::instrument(level = "info", skip(conn))]
 pub fn get_base_ortho_by_origin(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     o: Word,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
     use crate::schema::orthotopes::{base, origin, table as orthotopes};
@@ -379,19 +395,16 @@ pub fn get_base_ortho_by_origin(
         FilterDsl::filter(orthotopes, origin.eq(o).and(base.eq(true))),
         schema::orthotopes::information,
     )
-    .load(conn.expect("don't use test connections in production"))?;
+    .load(conn)?;
 
-    let res: Vec<Ortho> = results
-        .iter()
-        .map(|x| bincode::deserialize(x).expect("deserialization should succeed"))
-        .collect();
+    let res = ortho_encoding::decode_all(&results)?;
 
     Ok(res)
 }
 
 #[tracing::instrument(level = "info", skip(conn))]
 pub fn get_ortho_by_origin_batch(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     o: HashSet<Word>,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
     use crate::schema::orthotopes::{origin, table as orthotopes};
@@ -400,23 +413,22 @@ pub fn get_ortho_by_origin_batch(
         FilterDsl::filter(orthotopes, origin.eq(any(Vec::from_iter(o)))),
         schema::orthotopes::information,
     )
-    .load(conn.expect("don't use test connections in production"))?;
+    .load(conn)?;
 
-    let res: Vec<Ortho> = results
-        .iter()
-        .map(|x| bincode::deserialize(x).expect("deserialization should succeed"))
-        .collect();
+    let res = ortho_encoding::decode_all(&results)?;
 
     Ok(res)
 }
 
 pub fn ortho_to_orthotope(ortho: &Ortho) -> NewOrthotope {
-    let information = bincode::serialize(&ortho).expect("serialization should work");
+    let information = ortho_encoding::encode(ortho);
     let origin = ortho.get_origin().to_owned();
     let hop = Vec::from_iter(ortho.get_hop());
     let contents = Vec::from_iter(ortho.get_contents());
-    let info_hash = pair_todo_handler::data_vec_to_signed_int(&information);
+    let info_hash = ortho_encoding::content_hash(ortho);
     let base = ortho.is_base();
+    let dims = ortho.get_shape();
+    let volume = ortho.get_volume() as i32;
     NewOrthotope {
         information,
         origin,
@@ -424,12 +436,14 @@ pub fn ortho_to_orthotope(ortho: &Ortho) -> NewOrthotope {
         contents,
         info_hash,
         base,
+        dims,
+        volume,
     }
 }
 
 #[tracing::instrument(level = "info", skip(conn))]
 fn get_ortho_by_hop(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     other_hop: Vec<Word>,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
     use crate::schema::orthotopes::{hop, table as orthotopes};
@@ -437,19 +451,16 @@ fn get_ortho_by_hop(
         orthotopes.filter(hop.overlaps_with(other_hop)),
         schema::orthotopes::information,
     )
-    .load(conn.expect("don't use test connections in production"))?;
+    .load(conn)?;
 
-    let res: Vec<Ortho> = results
-        .iter()
-        .map(|x| bincode::deserialize(x).expect("deserialization should succeed"))
-        .collect();
+    let res = ortho_encoding::decode_all(&results)?;
 
     Ok(res)
 }
 
 #[tracing::instrument(level = "info", skip(conn))]
 fn get_base_ortho_by_hop(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     other_hop: Vec<Word>,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
     use crate::schema::orthotopes::{base, hop, table as orthotopes};
@@ -457,19 +468,16 @@ fn get_base_ortho_by_hop(
         orthotopes.filter(hop.overlaps_with(other_hop).and(base.eq(true))),
         schema::orthotopes::information,
     )
-    .load(conn.expect("don't use test connections in production"))?;
+    .load(conn)?;
 
-    let res: Vec<Ortho> = results
-        .iter()
-        .map(|x| bincode::deserialize(x).expect("deserialization should succeed"))
-        .collect();
+    let res = ortho_encoding::decode_all(&results)?;
 
     Ok(res)
 }
 
 #[tracing::instrument(level = "info", skip(conn))]
 fn get_ortho_by_contents(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     other_contents: Vec<Word>,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
     use crate::schema::orthotopes::{contents, table as orthotopes};
@@ -477,19 +485,16 @@ fn get_ortho_by_contents(
         orthotopes.filter(contents.overlaps_with(other_contents)),
         schema::orthotopes::information,
     )
-    .load(conn.expect("don't use test connections in production"))?;
+    .load(conn)?;
 
-    let res: Vec<Ortho> = results
-        .iter()
-        .map(|x| bincode::deserialize(x).expect("deserialization should succeed"))
-        .collect();
+    let res = ortho_encoding::decode_all(&results)?;
 
     Ok(res)
 }
 
 #[tracing::instrument(level = "info", skip(conn))]
 fn get_base_ortho_by_contents(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     other_contents: Vec<Word>,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
     use crate::schema::orthotopes::{base, contents, table as orthotopes};
@@ -497,19 +502,16 @@ fn get_base_ortho_by_contents(
         orthotopes.filter(contents.overlaps_with(other_contents).and(base.eq(true))),
         schema::orthotopes::information,
     )
-    .load(conn.expect("don't use test connections in production"))?;
+    .load(conn)?;
 
-    let res: Vec<Ortho> = results
-        .iter()
-        .map(|x| bincode::deserialize(x).expect("deserialization should succeed"))
-        .collect();
+    let res = ortho_encoding::decode_all(&results)?;
 
     Ok(res)
 }
 
 #[tracing::instrument(level = "info", skip(conn))]
 pub(crate) fn phrase_exists_db_filter(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     left: HashSet<i64>,
     right: HashSet<i64>,
 ) -> Result<HashSet<i64>, anyhow::Error> {
@@ -521,7 +523,7 @@ pub(crate) fn phrase_exists_db_filter(
         ),
         crate::schema::phrases::words_hash,
     )
-    .load(conn.expect("do not pass a test dummy in production"))?
+    .load(conn)?
     .iter()
     .cloned()
     .collect();
@@ -533,7 +535,7 @@ pub(crate) fn phrase_exists_db_filter(
         ),
         crate::schema::phrases::words_hash,
     )
-    .load(conn.expect("do not pass a test dummy in production"))?
+    .load(conn)?
     .iter()
     .cloned()
     .collect();
@@ -543,7 +545,7 @@ pub(crate) fn phrase_exists_db_filter(
 
 #[tracing::instrument(level = "info", skip(conn))]
 pub(crate) fn phrase_exists_db_filter_head(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     left: HashSet<i64>,
 ) -> Result<HashSet<i64>, anyhow::Error> {
     use crate::phrases::dsl::phrases;
@@ -554,7 +556,7 @@ pub(crate) fn phrase_exists_db_filter_head(
         ),
         crate::schema::phrases::words_hash,
     )
-    .load(conn.expect("do not pass a test dummy in production"))?
+    .load(conn)?
     .iter()
     .cloned()
     .collect();
@@ -564,7 +566,7 @@ pub(crate) fn phrase_exists_db_filter_head(
 
 #[tracing::instrument(level = "info", skip(conn))]
 pub(crate) fn phrase_exists_db_filter_tail(
-    conn: Option<&PgConnection>,
+    conn: &PgConnection,
     right: HashSet<i64>,
 ) -> Result<HashSet<i64>, anyhow::Error> {
     use crate::phrases::dsl::phrases;
@@ -576,7 +578,7 @@ pub(crate) fn phrase_exists_db_filter_tail(
         ),
         crate::schema::phrases::words_hash,
     )
-    .load(conn.expect("do not pass a test dummy in production"))?
+    .load(conn)?
     .iter()
     .cloned()
     .collect();
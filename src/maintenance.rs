@@ -0,0 +1,134 @@
+use anyhow::Context;
+use diesel::{ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl};
+
+use crate::{ints_to_big_int, ortho_encoding, string_to_signed_int, vec_of_words_to_big_int, Word};
+
+pub fn rehash_all(conn: &PgConnection) -> Result<Vec<(&'static str, usize)>, anyhow::Error> {
+    conn.build_transaction().serializable().run(|| {
+        Ok(vec![
+            ("words", rehash_words(conn)?),
+            ("sentences", rehash_sentences(conn)?),
+            ("pairs", rehash_pairs(conn)?),
+            ("phrases", rehash_phrases(conn)?),
+            ("orthotopes", rehash_orthotopes(conn)?),
+        ])
+    })
+}
+
+fn rehash_words(conn: &PgConnection) -> Result<usize, diesel::result::Error> {
+    use crate::schema::words::{dsl::words, id, word, word_hash};
+    let rows: Vec<(i32, String, i64)> = words.select((id, word, word_hash)).load(conn)?;
+
+    let mut changed = 0;
+    for (pk, w, old_hash) in rows {
+        let new_hash = string_to_signed_int(&w);
+        if new_hash != old_hash {
+            diesel::update(words.filter(id.eq(pk)))
+                .set(word_hash.eq(new_hash))
+                .execute(conn)?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+fn rehash_sentences(conn: &PgConnection) -> Result<usize, diesel::result::Error> {
+    use crate::schema::sentences::{dsl::sentences, id, sentence, sentence_hash};
+    let rows: Vec<(i32, String, i64)> =
+        sentences.select((id, sentence, sentence_hash)).load(conn)?;
+
+    let mut changed = 0;
+    for (pk, s, old_hash) in rows {
+        let new_hash = string_to_signed_int(&s);
+        if new_hash != old_hash {
+            diesel::update(sentences.filter(id.eq(pk)))
+                .set(sentence_hash.eq(new_hash))
+                .execute(conn)?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+fn rehash_pairs(conn: &PgConnection) -> Result<usize, diesel::result::Error> {
+    use crate::schema::pairs::{dsl::pairs, first_word, id, pair_hash, second_word};
+    let rows: Vec<(i32, Word, Word, i64)> = pairs
+        .select((id, first_word, second_word, pair_hash))
+        .load(conn)?;
+
+    let mut changed = 0;
+    for (pk, f, s, old_hash) in rows {
+        let new_hash = ints_to_big_int(f, s);
+        if new_hash != old_hash {
+            diesel::update(pairs.filter(id.eq(pk)))
+                .set(pair_hash.eq(new_hash))
+                .execute(conn)?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+fn rehash_phrases(conn: &PgConnection) -> Result<usize, diesel::result::Error> {
+    use crate::schema::phrases::{dsl::phrases, id, phrase_head, phrase_tail, words, words_hash};
+    let rows: Vec<(i32, Vec<Word>, i64)> = phrases.select((id, words, words_hash)).load(conn)?;
+
+    let mut changed = 0;
+    for (pk, ws, old_hash) in rows {
+        let new_hash = vec_of_words_to_big_int(ws.clone());
+        if new_hash != old_hash {
+            diesel::update(phrases.filter(id.eq(pk)))
+                .set((
+                    words_hash.eq(new_hash),
+                    phrase_head.eq(vec_of_words_to_big_int(ws[..ws.len() - 1].to_vec())),
+                    phrase_tail.eq(vec_of_words_to_big_int(ws[1..].to_vec())),
+                ))
+                .execute(conn)?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+fn rehash_orthotopes(conn: &PgConnection) -> Result<usize, anyhow::Error> {
+    use crate::schema::orthotopes::{dsl::orthotopes, id, info_hash, information};
+    let rows: Vec<(i32, Vec<u8>, i64)> =
+        orthotopes.select((id, information, info_hash)).load(conn)?;
+
+    let mut changed = 0;
+    for (pk, info, old_hash) in rows {
+        let ortho = ortho_encoding::decode(&info)
+            .with_context(|| format!("orthotope {} does not decode", pk))?;
+        let new_hash = ortho_encoding::content_hash(&ortho);
+        if new_hash != old_hash {
+            diesel::update(orthotopes.filter(id.eq(pk)))
+                .set(info_hash.eq(new_hash))
+                .execute(conn)?;
+            changed += 1;
+        }
+    }
+    Ok(changed)
+}
+
+// Rewrites the orthotopes stored in an older encoding version in the current one. Their
+// info_hash is a hash of the ortho rather than of its bytes, so it stays the same.
+pub fn reencode_orthotopes(conn: &PgConnection) -> Result<usize, anyhow::Error> {
+    use crate::schema::orthotopes::{dsl::orthotopes, id, information};
+    conn.build_transaction().serializable().run(|| {
+        let rows: Vec<(i32, Vec<u8>)> = orthotopes.select((id, information)).load(conn)?;
+
+        let mut changed = 0;
+        for (pk, info) in rows {
+            if ortho_encoding::version(&info) == ortho_encoding::CURRENT_VERSION {
+                continue;
+            }
+            let ortho = ortho_encoding::decode(&info)
+                .with_context(|| format!("orthotope {} does not decode", pk))?;
+            diesel::update(orthotopes.filter(id.eq(pk)))
+                .set(information.eq(ortho_encoding::encode(&ortho)))
+                .execute(conn)?;
+            changed += 1;
+        }
+        Ok(changed)
+    })
+}
@@ -1,9 +1,11 @@
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::todo_domain::TodoDomain;
 use crate::Word;
 
 use super::schema::books;
+use super::schema::hash_collisions;
 use super::schema::orthotopes;
 use super::schema::pairs;
 use super::schema::phrases;
@@ -28,14 +30,15 @@ pub struct Book {
 #[derive(Insertable, Debug, Clone, PartialEq, Eq, Hash)]
 #[table_name = "todos"]
 pub struct NewTodo {
-    pub domain: String,
+    pub domain: TodoDomain,
     pub other: i32,
 }
 
-#[derive(Queryable, Serialize, Deserialize, Debug)]
+#[derive(Queryable, QueryableByName, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[table_name = "todos"]
 pub struct Todo {
     pub id: i32,
-    pub domain: String,
+    pub domain: TodoDomain,
     pub other: i32,
 }
 
@@ -78,6 +81,8 @@ pub struct NewOrthotope {
     pub contents: Vec<Word>,
     pub base: bool,
     pub info_hash: i64,
+    pub dims: String,
+    pub volume: i32,
 }
 
 #[derive(Queryable, Debug)]
@@ -89,6 +94,8 @@ pub struct Orthotope {
     pub contents: Vec<Word>,
     pub base: bool,
     pub info_hash: i64,
+    pub dims: String,
+    pub volume: i32,
 }
 
 #[derive(Insertable, Debug, PartialEq, Eq, Hash, Clone)]
@@ -129,3 +136,12 @@ pub struct ExNihilo {
     pub first_word: Word,
     pub second_word: Word,
 }
+
+#[derive(Insertable, Debug, PartialEq, Eq)]
+#[table_name = "hash_collisions"]
+pub struct NewHashCollision {
+    pub table_name: String,
+    pub hash: i64,
+    pub existing_id: i32,
+    pub attempted: String,
+}
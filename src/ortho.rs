@@ -6,51 +6,124 @@ use std::collections::{BTreeMap, HashSet};
 
 use crate::{vec_of_big_ints_to_big_int, vec_of_words_to_big_int, Word};
 
+// An orthotope, stored densely. Every ortho is a box: each axis has a length, and there is a
+// word at every location whose distance along each axis is at most that length.
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Eq, PartialOrd, Ord)]
 pub struct Ortho {
-    pub info: BTreeMap<Location, Word>,
+    // The axis names in ascending order. An axis is named by the word one step along it.
+    axes: Vec<Word>,
+    // The length of each axis, in the order of `axes`.
+    shape: Vec<usize>,
+    // The word at every location, row-major over `shape` with the last axis varying fastest.
+    words: Vec<Word>,
 }
 
 impl Ortho {
     pub fn get_origin(&self) -> Word {
-        let (_k, v) = self
-            .info
-            .iter()
-            .find(|(k, _v)| k.length() == 0)
-            .expect("all orthos should have an origin");
-        *v
+        self.words[0]
     }
 
     pub fn get_hop(&self) -> HashSet<Word> {
-        self.info
-            .iter()
-            .filter_map(|(k, v)| if k.length() == 1 { Some(*v) } else { None })
+        (0..self.axes.len())
+            .map(|axis| self.words[self.stride(axis)])
             .collect()
     }
 
     pub fn get_contents(&self) -> HashSet<Word> {
-        self.info
-            .iter()
-            .filter_map(|(k, v)| if k.length() > 1 { Some(*v) } else { None })
-            .collect()
+        self.words_where(|distance| distance > 1)
     }
 
     pub fn is_base(&self) -> bool {
-        self.get_bottom_right_corner().each_location_is_length_one()
+        self.shape.iter().all(|length| *length == 1)
     }
 
     pub fn new(a: Word, b: Word, c: Word, d: Word) -> Ortho {
+        debug_assert_ne!(b, c, "the two axes of a square must differ");
+        let (axes, words) = if b < c {
+            (vec![b, c], vec![a, c, b, d])
+        } else {
+            (vec![c, b], vec![a, b, c, d])
+        };
         Ortho {
-            info: btreemap! {Location { info: btreemap! {} } => a, Location {
-                info: btreemap! {b => 1},
-            } => b, Location {
-                info: btreemap! {c => 1},
-            } => c, Location {
-                info: btreemap! {b => 1, c => 1},
-            } => d},
+            axes,
+            shape: vec![1, 1],
+            words,
         }
     }
 
+    // Converts from the form orthos had before they were stored densely, one entry per location.
+    // Returns None unless the locations fill a box.
+    pub fn from_map(info: BTreeMap<Location, Word>) -> Option<Ortho> {
+        let mut lengths: BTreeMap<Word, usize> = BTreeMap::new();
+        for location in info.keys() {
+            for (axis, distance) in &location.info {
+                let length = lengths.entry(*axis).or_insert(0);
+                *length = (*length).max(*distance);
+            }
+        }
+        let mut ortho = Ortho {
+            axes: lengths.keys().cloned().collect(),
+            shape: lengths.values().cloned().collect(),
+            words: vec![],
+        };
+        if ortho.shape.contains(&0) || info.len() != ortho.cell_count() {
+            return None;
+        }
+        ortho.words = (0..info.len())
+            .map(|index| info.get(&ortho.location_of(index)).copied())
+            .collect::<Option<Vec<Word>>>()?;
+        let ortho = ortho.canonicalize();
+        ortho.is_well_formed().then_some(ortho)
+    }
+
+    pub fn to_map(&self) -> BTreeMap<Location, Word> {
+        self.cells().collect()
+    }
+
+    // Whether the fields describe a box, which a decoded ortho need not.
+    pub fn is_well_formed(&self) -> bool {
+        !self.axes.is_empty()
+            && self.axes.len() == self.shape.len()
+            && self.axes.windows(2).all(|pair| pair[0] < pair[1])
+            && !self.shape.contains(&0)
+            && self
+                .shape
+                .iter()
+                .try_fold(1usize, |acc, l| acc.checked_mul(l + 1))
+                == Some(self.words.len())
+    }
+
+    // The same ortho with each axis named by the word one step along it, and the axes in ascending
+    // order of name. Naming an axis only picks out which words lie along it, so orthos that differ
+    // in how their axes are named or ordered, or that are transposes with the same words at every
+    // location, have the same canonical form. Every constructor returns canonical orthos.
+    pub fn canonicalize(&self) -> Ortho {
+        let names: Vec<Word> = (0..self.axes.len())
+            .map(|axis| self.words[self.stride(axis)])
+            .collect();
+        let mut order: Vec<usize> = (0..self.axes.len()).collect();
+        order.sort_by_key(|axis| names[*axis]);
+        let mut canonical = Ortho {
+            axes: order.iter().map(|axis| names[*axis]).collect(),
+            shape: order.iter().map(|axis| self.shape[*axis]).collect(),
+            words: vec![],
+        };
+        let strides: Vec<usize> = order.iter().map(|axis| self.stride(*axis)).collect();
+        canonical.words = (0..self.words.len())
+            .map(|index| {
+                let mut from = 0;
+                canonical
+                    .for_each_distance(index, |axis, distance| from += distance * strides[axis]);
+                self.words[from]
+            })
+            .collect();
+        canonical
+    }
+
+    pub fn is_equivalent(&self, other: &Ortho) -> bool {
+        self.canonicalize() == other.canonicalize()
+    }
+
     pub fn axis_has_phrase(&self, phrase: &[Word], loc: &Location, axis: Word) -> bool {
         phrase
             .iter()
@@ -95,61 +168,73 @@ impl Ortho {
                 starting_location
                     .missing_axes(&hop)
                     .into_iter()
-                    .any(|axis| self.axis_has_exact_phrase(phrase, starting_location, axis))
+                    .any(|axis| self.axis_has_exact_phrase(phrase, &starting_location, axis))
             })
     }
 
-    pub fn get_bottom_right_corner(&self) -> &Location {
-        self.info
-            .keys()
-            .max_by(|left, right| left.length().cmp(&right.length()))
-            .expect("don't get the bottom right corner of empty orthos")
+    pub fn get_bottom_right_corner(&self) -> Location {
+        self.location_of(self.words.len() - 1)
     }
 
     pub fn get_dims(&self) -> BTreeMap<usize, usize> {
-        self.get_bottom_right_corner().dims()
+        let mut res: BTreeMap<usize, usize> = btreemap! {};
+        self.shape
+            .iter()
+            .for_each(|length| *res.entry(*length).or_insert(0) += 1);
+        res
+    }
+
+    // The dims as stored in the orthotopes.dims column, e.g. "1,1,2".
+    pub fn get_shape(&self) -> String {
+        dims_to_shape(&self.get_dims())
+    }
+
+    pub fn get_volume(&self) -> usize {
+        self.words.len()
     }
 
     pub fn zip_up(l: &Ortho, r: &Ortho, old_axis_to_new_axis: &BTreeMap<Word, Word>) -> Ortho {
         let shift_axis = r.get_origin();
-        let right_with_lefts_coordinate_system: BTreeMap<Location, Word> = r
-            .info
-            .iter()
-            .map(|(k, v)| (k.map_location(old_axis_to_new_axis), *v))
-            .collect();
-        let shifted_right: BTreeMap<Location, Word> = right_with_lefts_coordinate_system
-            .iter()
-            .map(|(k, v)| (k.shift_location(shift_axis), *v))
-            .collect();
-        let combined: BTreeMap<Location, Word> =
-            l.info.clone().into_iter().chain(shifted_right).collect();
-        Ortho { info: combined }
+        let shifted_right = r.cells().map(|(k, v)| {
+            (
+                k.map_location(old_axis_to_new_axis)
+                    .shift_location(shift_axis),
+                v,
+            )
+        });
+        let combined: BTreeMap<Location, Word> = l.cells().chain(shifted_right).collect();
+        Ortho::from_map(combined).expect("zipping up two boxes should make a box")
     }
 
     pub fn name_at_location(&self, location: &Location) -> Word {
-        *self
-            .info
-            .get(location)
+        self.optional_name_at_location(location)
             .expect("locations must be present to be queried")
     }
 
     pub fn optional_name_at_location(&self, location: &Location) -> Option<Word> {
-        self.info.get(location).copied()
+        let mut distances = location.info.iter().peekable();
+        let mut index = 0;
+        for (axis, length) in self.axes.iter().zip(&self.shape) {
+            let distance = distances
+                .next_if(|(a, _d)| *a == axis)
+                .map_or(0, |(_a, d)| *d);
+            if distance > *length {
+                return None;
+            }
+            index = index * (length + 1) + distance;
+        }
+        match distances.next() {
+            Some(_) => None,
+            None => Some(self.words[index]),
+        }
     }
 
     pub fn get_dimensionality(&self) -> usize {
-        self.info
-            .keys()
-            .max_by(|left, right| left.length().cmp(&right.length()))
-            .expect("empty orthos are invalid")
-            .length()
+        self.shape.iter().sum()
     }
 
     pub fn get_names_at_distance(&self, dist: usize) -> HashSet<Word> {
-        self.info
-            .iter()
-            .filter_map(|(k, v)| if k.length() == dist { Some(*v) } else { None })
-            .collect()
+        self.words_where(|distance| distance == dist)
     }
 
     pub fn zip_over(
@@ -159,56 +244,55 @@ impl Ortho {
         shift_axis: Word,
     ) -> Ortho {
         let right_column = r.get_end(shift_axis);
-        let shifted: BTreeMap<Location, Word> = right_column
-            .into_iter()
-            .map(|(k, v)| (k.add(shift_axis), v))
-            .collect();
-        let mapped = shifted
+        let mapped = right_column
             .into_iter()
-            .map(|(k, v)| (k.map_location(mapping), v));
-        let combined: BTreeMap<Location, Word> = l.info.clone().into_iter().chain(mapped).collect();
+            .map(|(k, v)| (k.add(shift_axis).map_location(mapping), v));
+        let combined: BTreeMap<Location, Word> = l.cells().chain(mapped).collect();
 
-        Ortho { info: combined }
+        Ortho::from_map(combined).expect("zipping over two boxes should make a box")
     }
 
     pub fn axis_length(&self, name: Word) -> usize {
-        self.info
-            .keys()
-            .max_by(|left, right| left.count_axis(name).cmp(&right.count_axis(name)))
-            .expect("no empty orthos")
-            .count_axis(name)
+        match self.axes.binary_search(&name) {
+            Ok(i) => self.shape[i],
+            Err(_) => 0,
+        }
     }
 
     fn get_end(&self, shift_axis: Word) -> BTreeMap<Location, Word> {
-        let axis_length = self.axis_length(shift_axis);
-        self.info
-            .clone()
-            .into_iter()
-            .filter(|(k, _v)| k.count_axis(shift_axis) == axis_length)
-            .collect()
+        match self.axes.binary_search(&shift_axis) {
+            Ok(axis) => self
+                .cells_where(|index| self.distance_along(index, axis) == self.shape[axis])
+                .into_iter()
+                .collect(),
+            Err(_) => self.cells().collect(),
+        }
     }
 
-    fn location_at_name(&self, name: Word) -> Vec<&Location> {
-        self.info
-            .iter()
-            .filter_map(|(loc, n)| if *n == name { Some(loc) } else { None })
+    fn location_at_name(&self, name: Word) -> Vec<Location> {
+        self.cells_where(|index| self.words[index] == name)
+            .into_iter()
+            .map(|(loc, _name)| loc)
             .collect()
     }
 
-    pub fn to_vec(&self) -> Vec<(&Location, Word)> {
-        self.info.iter().map(|(a, b)| (a, *b)).collect()
+    pub fn to_vec(&self) -> Vec<(Location, Word)> {
+        self.cells().collect()
     }
 
     pub fn get_vocabulary(&self) -> impl Iterator<Item = i32> + '_ {
-        self.info.iter().map(|(_, b)| *b)
+        self.words.iter().copied()
     }
 
     pub fn phrases(&self, shift_axis: Word) -> Vec<Vec<Word>> {
         let length = self.axis_length(shift_axis);
-        self.info
-            .iter()
-            .filter(|(loc, _name)| loc.does_not_have_axis(shift_axis))
-            .map(|(loc, _name)| self.extract_phrase_along(shift_axis, length, loc))
+        let starts = match self.axes.binary_search(&shift_axis) {
+            Ok(axis) => self.cells_where(|index| self.distance_along(index, axis) == 0),
+            Err(_) => self.cells().collect(),
+        };
+        starts
+            .into_iter()
+            .map(|(loc, _name)| self.extract_phrase_along(shift_axis, length, &loc))
             .collect()
     }
 
@@ -297,6 +381,69 @@ impl Ortho {
         phrases_per_right.sort_unstable();
         vec_of_big_ints_to_big_int(phrases_per_right)
     }
+
+    fn cell_count(&self) -> usize {
+        self.shape.iter().map(|length| length + 1).product()
+    }
+
+    // How far apart neighbours along the axis at `axis` are in `words`.
+    fn stride(&self, axis: usize) -> usize {
+        self.shape[axis + 1..]
+            .iter()
+            .map(|length| length + 1)
+            .product()
+    }
+
+    fn distance_along(&self, index: usize, axis: usize) -> usize {
+        index / self.stride(axis) % (self.shape[axis] + 1)
+    }
+
+    // Calls `f` with each axis position and the distance along it of the word at `index`.
+    fn for_each_distance(&self, index: usize, mut f: impl FnMut(usize, usize)) {
+        let mut rest = index;
+        for (axis, length) in self.shape.iter().enumerate().rev() {
+            f(axis, rest % (length + 1));
+            rest /= length + 1;
+        }
+    }
+
+    fn location_of(&self, index: usize) -> Location {
+        let mut info = BTreeMap::new();
+        self.for_each_distance(index, |axis, distance| {
+            if distance > 0 {
+                info.insert(self.axes[axis], distance);
+            }
+        });
+        Location { info }
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (Location, Word)> + '_ {
+        self.words
+            .iter()
+            .enumerate()
+            .map(|(index, word)| (self.location_of(index), *word))
+    }
+
+    // Only builds the locations of the words at the indexes that pass `keep`.
+    fn cells_where(&self, keep: impl Fn(usize) -> bool) -> Vec<(Location, Word)> {
+        (0..self.words.len())
+            .filter(|index| keep(*index))
+            .map(|index| (self.location_of(index), self.words[index]))
+            .collect()
+    }
+
+    // The words at locations whose distance from the origin passes `keep`.
+    fn words_where(&self, keep: impl Fn(usize) -> bool) -> HashSet<Word> {
+        self.words
+            .iter()
+            .enumerate()
+            .filter_map(|(index, word)| {
+                let mut distance = 0;
+                self.for_each_distance(index, |_, d| distance += d);
+                keep(distance).then_some(*word)
+            })
+            .collect()
+    }
 }
 
 #[derive(Serialize, Deserialize, Ord, PartialOrd, PartialEq, Eq, Debug, Clone)]
@@ -325,14 +472,6 @@ impl Location {
         Location { info: other }
     }
 
-    fn dims(&self) -> BTreeMap<usize, usize> {
-        let mut res: BTreeMap<usize, usize> = btreemap! {};
-        self.info
-            .values()
-            .for_each(|v| *res.entry(*v).or_insert(0) += 1);
-        res
-    }
-
     pub fn count_axis(&self, axis: Word) -> usize {
         *self.info.get(&axis).unwrap_or(&0)
     }
@@ -347,10 +486,6 @@ impl Location {
         Location { info: res }
     }
 
-    fn each_location_is_length_one(&self) -> bool {
-        self.info.values().all(|v| *v == 1)
-    }
-
     fn is_edge(&self, axes: &HashSet<Word>) -> bool {
         !self.missing_axes(axes).is_empty()
     }
@@ -376,10 +511,6 @@ impl Location {
         }
     }
 
-    fn does_not_have_axis(&self, shift_axis: Word) -> bool {
-        !self.info.contains_key(&shift_axis)
-    }
-
     fn subtract_adjacent_for_single_axis_name(&self, other: &Location) -> Word {
         *self
             .info
@@ -390,6 +521,12 @@ impl Location {
     }
 }
 
+pub fn dims_to_shape(dims: &BTreeMap<usize, usize>) -> String {
+    dims.iter()
+        .flat_map(|(length, count)| std::iter::repeat_n(length.to_string(), *count))
+        .join(",")
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
@@ -658,6 +795,61 @@ mod tests {
         assert_eq!(example_ortho, rotated_ortho);
     }
 
+    #[test]
+    fn it_canonicalizes_regardless_of_how_axes_are_named_or_ordered() {
+        // a b c
+        // d e f
+        let abcdef = Ortho::zip_over(
+            &Ortho::new(1, 2, 4, 5),
+            &Ortho::new(2, 3, 5, 6),
+            &btreemap! { 3 => 2, 5 => 4 },
+            3,
+        );
+        let down_then_across = Ortho {
+            axes: vec![70, 80],
+            shape: vec![1, 2],
+            words: vec![1, 2, 3, 4, 5, 6],
+        };
+        let across_then_down = Ortho {
+            axes: vec![70, 80],
+            shape: vec![2, 1],
+            words: vec![1, 4, 2, 5, 3, 6],
+        };
+
+        assert_eq!(abcdef.canonicalize(), abcdef);
+        assert_eq!(down_then_across.canonicalize(), abcdef);
+        assert_eq!(across_then_down.canonicalize(), abcdef);
+        assert!(down_then_across.is_equivalent(&across_then_down));
+        assert!(!down_then_across.is_equivalent(&Ortho {
+            axes: vec![70, 80],
+            shape: vec![1, 2],
+            words: vec![1, 2, 3, 4, 6, 5],
+        }));
+    }
+
+    #[test]
+    fn it_is_transposition_independent_only_when_the_words_coincide() {
+        let square = Ortho {
+            axes: vec![70, 80],
+            shape: vec![1, 1],
+            words: vec![1, 2, 3, 4],
+        };
+        let transposed = Ortho {
+            axes: vec![70, 80],
+            shape: vec![1, 1],
+            words: vec![1, 3, 2, 4],
+        };
+        let mirrored = Ortho {
+            axes: vec![70, 80],
+            shape: vec![1, 1],
+            words: vec![2, 1, 4, 3],
+        };
+
+        assert_eq!(square.canonicalize(), Ortho::new(1, 2, 3, 4));
+        assert!(square.is_equivalent(&transposed));
+        assert!(!square.is_equivalent(&mirrored));
+    }
+
     #[test]
     fn it_hashes_consistently() {
         let example_ortho = Ortho::new(1, 2, 3, 4);
@@ -683,7 +875,7 @@ mod tests {
             7 => 3
         };
 
-        let actual = Ortho::zip_up(&l, &r, &mapping).info;
+        let actual = Ortho::zip_up(&l, &r, &mapping).to_map();
         let expected = btreemap! {
             Location { info: btreemap!{} } => 1,
             Location { info: btreemap!{2 => 1} } => 2,
@@ -715,6 +907,18 @@ mod tests {
         assert_eq!(o.get_dimensionality(), 2);
     }
 
+    #[test]
+    fn it_has_a_shape_and_volume() {
+        let o = Ortho::new(1, 2, 3, 4);
+        assert_eq!(o.get_shape(), "1,1");
+        assert_eq!(o.get_volume(), 4);
+
+        let r = Ortho::new(2, 5, 4, 6);
+        let over = Ortho::zip_over(&o, &r, &btreemap! { 5 => 2, 4 => 3 }, 5);
+        assert_eq!(over.get_shape(), "1,2");
+        assert_eq!(over.get_volume(), 6);
+    }
+
     #[test]
     fn it_gets_all_names_at_a_distance() {
         let o = Ortho::new(1, 2, 3, 4);
@@ -744,7 +948,7 @@ mod tests {
 
         let shift_axis = 5;
 
-        let actual = Ortho::zip_over(&l, &r, &mapping, shift_axis).info;
+        let actual = Ortho::zip_over(&l, &r, &mapping, shift_axis).to_map();
         let expected = btreemap! {
             Location { info: btreemap!{} } => 1,
             Location { info: btreemap!{2 => 1} } => 2,
@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ortho::{Location, Ortho},
+    pair_todo_handler::data_vec_to_signed_int,
+    Word,
+};
+
+// Stored orthos start with MAGIC and a version byte, followed by the payload for that version.
+// Rows written before the header existed are the bare bincode of the ortho, which never starts
+// with MAGIC since it opens with a small little-endian u64 location count. They read as version 0.
+const MAGIC: &[u8; 3] = b"PVO";
+
+// Version 0 and 1 hold an ortho as one entry per location, as `Ortho` was before it was dense.
+// Version 2 holds `Ortho` itself: its axes, shape and words.
+pub const LEGACY_VERSION: u8 = 0;
+pub const MAP_VERSION: u8 = 1;
+pub const CURRENT_VERSION: u8 = 2;
+
+#[derive(Serialize, Deserialize)]
+struct MapOrtho {
+    info: BTreeMap<Location, Word>,
+}
+
+pub fn encode(ortho: &Ortho) -> Vec<u8> {
+    let mut bytes = MAGIC.to_vec();
+    bytes.push(CURRENT_VERSION);
+    bytes.extend(bincode::serialize(ortho).expect("serialization should work"));
+    bytes
+}
+
+pub fn version(bytes: &[u8]) -> u8 {
+    match bytes.strip_prefix(MAGIC) {
+        Some(rest) => rest.first().copied().unwrap_or(LEGACY_VERSION),
+        None => LEGACY_VERSION,
+    }
+}
+
+// Reads any version this build knows, upgrading older ones to the current `Ortho`.
+pub fn decode(bytes: &[u8]) -> Result<Ortho, anyhow::Error> {
+    let version = version(bytes);
+    let ortho = match version {
+        LEGACY_VERSION => decode_map(bytes),
+        MAP_VERSION => decode_map(&bytes[MAGIC.len() + 1..]),
+        CURRENT_VERSION => bincode::deserialize::<Ortho>(&bytes[MAGIC.len() + 1..])
+            .map_err(anyhow::Error::from)
+            .and_then(|ortho| {
+                anyhow::ensure!(ortho.is_well_formed(), "ortho is not a box");
+                let ortho = ortho.canonicalize();
+                anyhow::ensure!(ortho.is_well_formed(), "ortho has two axes with one name");
+                Ok(ortho)
+            }),
+        unknown => anyhow::bail!("ortho is encoded with unknown version {}", unknown),
+    };
+    ortho.with_context(|| format!("ortho does not decode as version {}", version))
+}
+
+fn decode_map(payload: &[u8]) -> Result<Ortho, anyhow::Error> {
+    let map: MapOrtho = bincode::deserialize(payload)?;
+    Ortho::from_map(map.info).context("ortho locations do not fill a box")
+}
+
+pub fn decode_all(rows: &[Vec<u8>]) -> Result<Vec<Ortho>, anyhow::Error> {
+    rows.iter().map(|row| decode(row)).collect()
+}
+
+// The info_hash of an ortho. It hashes the canonical form of the ortho rather than its stored
+// bytes, so rewriting a row in a newer version keeps its hash, and the same ortho arriving in a
+// newer version, or with its axes named differently, conflicts with the old row instead of being
+// inserted again. It hashes the version 0 bytes, which is what info_hash was before there were
+// versions.
+pub fn content_hash(ortho: &Ortho) -> i64 {
+    let map = MapOrtho {
+        info: ortho.canonicalize().to_map(),
+    };
+    data_vec_to_signed_int(&bincode::serialize(&map).expect("serialization should work"))
+}
+
+#[cfg(test)]
+mod tests {
+    use maplit::btreemap;
+
+    use std::collections::HashSet;
+
+    use crate::{
+        fact_store::InMemoryFactStore,
+        ortho::Ortho,
+        ortho_encoding::{
+            content_hash, decode, encode, version, MapOrtho, CURRENT_VERSION, LEGACY_VERSION,
+            MAGIC, MAP_VERSION,
+        },
+        over_on_ortho_found_handler,
+        pair_todo_handler::data_vec_to_signed_int,
+        phrase_ortho_handler, up_handler, up_on_ortho_found_handler,
+    };
+
+    fn cube() -> Ortho {
+        Ortho::zip_up(
+            &Ortho::new(1, 2, 3, 4),
+            &Ortho::new(5, 6, 7, 8),
+            &btreemap! { 6 => 2, 7 => 3 },
+        )
+    }
+
+    fn legacy_bytes(ortho: &Ortho) -> Vec<u8> {
+        bincode::serialize(&MapOrtho {
+            info: ortho.to_map(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn it_round_trips_through_the_current_version() {
+        let o = cube();
+        let bytes = encode(&o);
+
+        assert_eq!(version(&bytes), CURRENT_VERSION);
+        assert_eq!(decode(&bytes).unwrap(), o);
+    }
+
+    #[test]
+    fn it_upgrades_rows_written_in_older_versions() {
+        let o = cube();
+        let legacy = legacy_bytes(&o);
+        let mut map_version = MAGIC.to_vec();
+        map_version.push(MAP_VERSION);
+        map_version.extend(&legacy);
+
+        assert_eq!(version(&legacy), LEGACY_VERSION);
+        assert_eq!(decode(&legacy).unwrap(), o);
+        assert_eq!(version(&map_version), MAP_VERSION);
+        assert_eq!(decode(&map_version).unwrap(), o);
+    }
+
+    #[test]
+    fn it_is_smaller_than_one_entry_per_location() {
+        let o = cube();
+
+        assert!(encode(&o).len() * 2 < legacy_bytes(&o).len());
+    }
+
+    #[test]
+    fn it_keeps_the_hash_of_rows_written_without_a_header() {
+        let o = cube();
+
+        assert_eq!(content_hash(&o), data_vec_to_signed_int(&legacy_bytes(&o)));
+    }
+
+    // The info_hash and bytes of every ortho found, which should be a single row.
+    fn rows(found: &[Vec<Ortho>]) -> HashSet<(i64, Vec<u8>)> {
+        assert!(found.iter().all(|orthos| !orthos.is_empty()));
+        found
+            .iter()
+            .flatten()
+            .map(|o| (content_hash(o), encode(o)))
+            .collect()
+    }
+
+    #[test]
+    fn it_gives_one_row_to_an_ortho_found_by_any_up_path() {
+        // a b   e f
+        // c d   g h
+        let (abcd, efgh) = (Ortho::new(1, 2, 3, 4), Ortho::new(5, 6, 7, 8));
+        let store = InMemoryFactStore::from_facts(
+            vec![abcd.clone(), efgh.clone()],
+            vec![
+                (1, 2),
+                (3, 4),
+                (1, 3),
+                (2, 4),
+                (5, 6),
+                (7, 8),
+                (5, 7),
+                (6, 8),
+                (1, 5),
+                (2, 6),
+                (3, 7),
+                (4, 8),
+            ],
+            vec![],
+        );
+
+        let found = [
+            up_handler::up_by_origin(&store, 1, 5).unwrap(),
+            up_handler::up_by_hop(&store, 2, 6).unwrap(),
+            up_handler::up_by_contents(&store, 4, 8).unwrap(),
+            up_on_ortho_found_handler::up_forward(&store, abcd).unwrap(),
+            up_on_ortho_found_handler::up_back(&store, efgh).unwrap(),
+        ];
+
+        assert_eq!(rows(&found).len(), 1);
+    }
+
+    #[test]
+    fn it_gives_one_row_to_an_ortho_found_by_any_over_path() {
+        // a b c
+        // d e f
+        let (abde, bcef) = (Ortho::new(1, 2, 4, 5), Ortho::new(2, 3, 5, 6));
+        let store = InMemoryFactStore::from_facts(
+            vec![abde.clone(), bcef.clone()],
+            vec![(1, 2), (2, 3), (4, 5), (5, 6), (1, 4), (2, 5), (3, 6)],
+            vec![vec![1, 2, 3], vec![4, 5, 6]],
+        );
+
+        let found = [
+            phrase_ortho_handler::over_by_origin(&store, vec![1, 2, 3]).unwrap(),
+            phrase_ortho_handler::over_by_hop(&store, vec![4, 5, 6]).unwrap(),
+            over_on_ortho_found_handler::over_forward(&store, abde).unwrap(),
+            over_on_ortho_found_handler::over_back(&store, bcef).unwrap(),
+        ];
+
+        assert_eq!(rows(&found).len(), 1);
+    }
+
+    #[test]
+    fn it_fails_on_bytes_that_are_not_an_ortho() {
+        let mut future = encode(&Ortho::new(1, 2, 3, 4));
+        future[3] = CURRENT_VERSION + 1;
+        assert!(decode(&future).is_err());
+
+        // Drop the last of the four words, fixing up their count so that the bincode still reads.
+        let mut not_a_box = encode(&Ortho::new(1, 2, 3, 4));
+        let words_count = not_a_box.len() - 8 - 4 * 4;
+        not_a_box[words_count] = 3;
+        not_a_box.truncate(not_a_box.len() - 4);
+        assert!(decode(&not_a_box).is_err());
+
+        assert!(decode(&encode(&Ortho::new(1, 2, 3, 4))[..10]).is_err());
+        assert!(decode(&[1, 0, 0]).is_err());
+        assert!(decode(&[0; 8]).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use itertools::Itertools;
+
+use crate::{
+    ortho::{Location, Ortho},
+    Word,
+};
+
+// Which axes of an ortho run down and across the grid. Axes are numbered from 0 in order of
+// decreasing length, so by default the longest axis runs across and its phrases read left to
+// right. Any other axes are sliced: the ortho is drawn as one grid per location along them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    pub rows: usize,
+    pub columns: usize,
+}
+
+impl Layout {
+    // Whether the layout fits orthos with this many axes.
+    pub fn check(&self, axes: usize) -> Result<(), anyhow::Error> {
+        anyhow::ensure!(
+            self.rows != self.columns && self.rows.max(self.columns) < axes,
+            "rows and columns must be two different axes below {}",
+            axes
+        );
+        Ok(())
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout {
+            rows: 1,
+            columns: 0,
+        }
+    }
+}
+
+// Draws the ortho as a grid of words with each column padded to its widest word. Orthos of more
+// than two dimensions are drawn as a sequence of grids, each under a line giving its distance
+// along every sliced axis, e.g. `dog=1, cat=0`, where an axis is named by its first word.
+pub fn render(
+    ortho: &Ortho,
+    layout: Layout,
+    names: &HashMap<Word, String>,
+) -> Result<String, anyhow::Error> {
+    let axes = display_axes(ortho);
+    layout.check(axes.len())?;
+    let (rows, columns) = (axes[layout.rows], axes[layout.columns]);
+    let sliced: Vec<Word> = axes
+        .into_iter()
+        .filter(|axis| *axis != rows && *axis != columns)
+        .collect();
+    let name = |word: Word| {
+        names
+            .get(&word)
+            .map(String::as_str)
+            .with_context(|| format!("word {} is not in the vocabulary", word))
+    };
+
+    let mut lines = vec![];
+    for slice in slices(ortho, &sliced) {
+        if !sliced.is_empty() {
+            let label = sliced
+                .iter()
+                .map(|axis| Ok(format!("{}={}", name(*axis)?, slice.count_axis(*axis))))
+                .collect::<Result<Vec<String>, anyhow::Error>>()?;
+            lines.push(label.join(", "));
+        }
+        let grid = (0..=ortho.axis_length(rows))
+            .map(|row| {
+                (0..=ortho.axis_length(columns))
+                    .map(|column| {
+                        let location = slice.add_n(rows, row).add_n(columns, column);
+                        name(ortho.name_at_location(&location))
+                    })
+                    .collect::<Result<Vec<&str>, anyhow::Error>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        lines.extend(aligned(&grid));
+    }
+    Ok(lines.join("\n"))
+}
+
+fn display_axes(ortho: &Ortho) -> Vec<Word> {
+    ortho
+        .get_hop()
+        .into_iter()
+        .sorted()
+        .sorted_by_key(|axis| std::cmp::Reverse(ortho.axis_length(*axis)))
+        .collect()
+}
+
+// The corner of every slice, with the last sliced axis varying fastest.
+fn slices(ortho: &Ortho, sliced: &[Word]) -> Vec<Location> {
+    sliced
+        .iter()
+        .fold(vec![Location::default()], |corners, axis| {
+            corners
+                .iter()
+                .flat_map(|corner| (0..=ortho.axis_length(*axis)).map(|d| corner.add_n(*axis, d)))
+                .collect()
+        })
+}
+
+fn aligned(grid: &[Vec<&str>]) -> Vec<String> {
+    let widths: Vec<usize> = (0..grid[0].len())
+        .map(|column| {
+            grid.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    grid.iter()
+        .map(|row| {
+            row.iter()
+                .zip(&widths)
+                .map(|(word, width)| format!("{:width$}", word, width = width))
+                .join(" ")
+                .trim_end()
+                .to_owned()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use maplit::btreemap;
+
+    use crate::{
+        ortho::Ortho,
+        ortho_render::{render, Layout},
+        Word,
+    };
+
+    fn names() -> HashMap<Word, String> {
+        ["a", "bb", "c", "ddd", "e", "f", "g", "h"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (i as Word + 1, name.to_string()))
+            .collect()
+    }
+
+    // a   bb c
+    // ddd e  f
+    fn abcdef() -> Ortho {
+        Ortho::zip_over(
+            &Ortho::new(1, 2, 4, 5),
+            &Ortho::new(2, 3, 5, 6),
+            &btreemap! { 3 => 2, 5 => 4 },
+            3,
+        )
+    }
+
+    #[test]
+    fn it_renders_a_square_as_an_aligned_grid() {
+        let actual = render(&abcdef(), Layout::default(), &names()).unwrap();
+
+        assert_eq!(actual, "a   bb c\nddd e  f");
+    }
+
+    #[test]
+    fn it_can_lay_the_axes_out_the_other_way() {
+        let layout = Layout {
+            rows: 0,
+            columns: 1,
+        };
+
+        let actual = render(&abcdef(), layout, &names()).unwrap();
+
+        assert_eq!(actual, "a  ddd\nbb e\nc  f");
+    }
+
+    #[test]
+    fn it_renders_higher_dimensions_as_labeled_slices() {
+        let cube = Ortho::zip_up(
+            &Ortho::new(1, 2, 3, 4),
+            &Ortho::new(5, 6, 7, 8),
+            &btreemap! { 6 => 2, 7 => 3 },
+        );
+
+        let actual = render(&cube, Layout::default(), &names()).unwrap();
+
+        assert_eq!(actual, "e=0\na bb\nc ddd\ne=1\ne f\ng h");
+    }
+
+    #[test]
+    fn it_fails_without_two_different_axes_or_a_name_for_every_word() {
+        let same = Layout {
+            rows: 0,
+            columns: 0,
+        };
+        let missing = Layout {
+            rows: 2,
+            columns: 0,
+        };
+
+        assert!(render(&abcdef(), same, &names()).is_err());
+        assert!(render(&abcdef(), missing, &names()).is_err());
+        assert!(render(&abcdef(), Layout::default(), &HashMap::new()).is_err());
+    }
+}
@@ -4,34 +4,21 @@ use itertools::Itertools;
 use maplit::{btreeset, hashmap};
 
 use crate::{
-    ortho::Ortho, phrase_ortho_handler::attempt_combine_over_with_phrases, vec_of_words_to_big_int,
-    Word, FailableWordToVecOfOrthosfn, FailableWordsetToWordset, FailableWordsetToWordTupleset,
+    fact_store::FactStore, ortho::Ortho, phrase_ortho_handler::attempt_combine_over_with_phrases,
+    vec_of_words_to_big_int, Word,
 };
 
-#[tracing::instrument(
-    level = "info",
-    skip(
-        conn,
-        get_ortho_by_origin_batch,
-        project_forward_batch,
-        get_phrases_with_matching_hashes,
-        phrase_exists_db_filter_head
-    )
-)]
+#[tracing::instrument(level = "info", skip(store))]
 pub(crate) fn over_forward(
-    conn: Option<&diesel::PgConnection>,
+    store: &impl FactStore,
     old_orthotope: crate::ortho::Ortho,
-    get_ortho_by_origin_batch: FailableWordToVecOfOrthosfn,
-    project_forward_batch: FailableWordsetToWordTupleset,
-    get_phrases_with_matching_hashes: FailableWordsetToWordset,
-    phrase_exists_db_filter_head: FailableWordsetToWordset
 ) -> Result<Vec<crate::ortho::Ortho>, anyhow::Error> {
     let all_phrases = old_orthotope.origin_phrases();
     let all_second_words = all_phrases.iter().map(|p| p[1]).collect();
-    let all_potential_orthos = get_ortho_by_origin_batch(conn, all_second_words)?;
+    let all_potential_orthos = store.get_ortho_by_origin_batch(all_second_words)?;
 
     if all_potential_orthos.is_empty() {
-        return Ok(vec![])
+        return Ok(vec![]);
     }
 
     let lasts = all_phrases
@@ -39,7 +26,7 @@ pub(crate) fn over_forward(
         .map(|old_phrase| old_phrase.last().expect("orthos cannot have empty phrases"))
         .copied()
         .collect::<HashSet<_>>();
-    let forwards = project_forward_batch(conn, lasts)?;
+    let forwards = store.project_forward_batch(lasts)?;
 
     let last_to_phrase = Itertools::into_group_map_by(all_phrases.iter(), |old_phrase| {
         old_phrase.last().expect("orthos cannot have empty phrases")
@@ -74,7 +61,7 @@ pub(crate) fn over_forward(
         })
         .collect::<HashSet<_>>();
 
-    let actual_phrases = get_phrases_with_matching_hashes(conn, desired_phrases)?;
+    let actual_phrases = store.get_phrases_with_matching_hashes(desired_phrases)?;
 
     let all_phrase_heads: HashSet<i64> = old_orthotope
         .all_full_length_phrases()
@@ -82,7 +69,7 @@ pub(crate) fn over_forward(
         .map(|p| vec_of_words_to_big_int(p.to_vec()))
         .collect();
 
-    let speculative_potential_phrases = phrase_exists_db_filter_head(conn, all_phrase_heads)?;
+    let speculative_potential_phrases = store.phrase_exists_db_filter_head(all_phrase_heads)?;
 
     let mut ans: Vec<Ortho> = vec![];
 
@@ -158,12 +145,8 @@ pub(crate) fn over_forward(
 }
 
 pub(crate) fn over_back(
-    conn: Option<&diesel::PgConnection>,
+    store: &impl FactStore,
     old_orthotope: crate::ortho::Ortho,
-    get_ortho_by_origin_batch: FailableWordToVecOfOrthosfn,
-    project_backward_batch: FailableWordsetToWordTupleset,
-    get_phrases_with_matching_hashes: FailableWordsetToWordset,
-    phrase_exists_db_filter_head: FailableWordsetToWordset,
 ) -> Result<Vec<crate::ortho::Ortho>, anyhow::Error> {
     let all_phrases = old_orthotope.origin_phrases();
 
@@ -171,13 +154,13 @@ pub(crate) fn over_back(
         .iter()
         .map(|old_phrase| old_phrase[0])
         .collect::<HashSet<_>>();
-    let backwards = project_backward_batch(conn, firsts)?;
+    let backwards = store.project_backward_batch(firsts)?;
 
     let all_first_words = backwards.iter().map(|(f, _s)| f).copied().collect();
-    let all_potential_orthos = get_ortho_by_origin_batch(conn, all_first_words)?;
+    let all_potential_orthos = store.get_ortho_by_origin_batch(all_first_words)?;
 
     if all_potential_orthos.is_empty() {
-        return Ok(vec![])
+        return Ok(vec![]);
     }
 
     let first_to_phrase = Itertools::into_group_map_by(all_phrases.iter(), |phrase| phrase[0]);
@@ -210,14 +193,14 @@ pub(crate) fn over_back(
         })
         .collect::<HashSet<_>>();
 
-    let actual_phrases = get_phrases_with_matching_hashes(conn, desired_phrases)?;
+    let actual_phrases = store.get_phrases_with_matching_hashes(desired_phrases)?;
     let all_phrase_tails: HashSet<i64> = old_orthotope
         .all_full_length_phrases()
         .iter()
         .map(|p| vec_of_words_to_big_int(p.to_vec()))
         .collect();
 
-    let speculative_potential_phrases = phrase_exists_db_filter_head(conn, all_phrase_tails)?;
+    let speculative_potential_phrases = store.phrase_exists_db_filter_tail(all_phrase_tails)?;
 
     let mut phrase_to_ortho: std::collections::HashMap<
         &Vec<i32>,
@@ -306,86 +289,28 @@ pub(crate) fn over_back(
 #[cfg(test)]
 mod tests {
     use crate::{
-        ortho::Ortho, over_on_ortho_found_handler::over_back,
-        over_on_ortho_found_handler::over_forward, vec_of_words_to_big_int, Word,
+        fact_store::InMemoryFactStore, ortho::Ortho, over_on_ortho_found_handler::over_back,
+        over_on_ortho_found_handler::over_forward,
     };
-    use diesel::PgConnection;
-    use maplit::{btreemap, hashset};
-    use std::collections::HashSet;
-
-    fn fake_forward_batch(
-        _conn: Option<&PgConnection>,
-        _from: HashSet<Word>,
-    ) -> Result<HashSet<(Word, Word)>, anyhow::Error> {
-        // a b  | b e
-        // c d  | d f
-        let pairs = hashset! { (2,4), (2, 5)};
-        Ok(pairs)
-    }
-
-    fn fake_get_phrases_with_matching_hashes(
-        _conn: Option<&PgConnection>,
-        all_phrases: HashSet<i64>,
-    ) -> Result<HashSet<i64>, anyhow::Error> {
-        Ok(all_phrases)
-    }
-
-    fn fake_phrase_exists_db_filter_head(
-        _conn: Option<&PgConnection>,
-        _head: HashSet<i64>,
-    ) -> Result<HashSet<i64>, anyhow::Error> {
-        let res = hashset! {
-            vec_of_words_to_big_int(vec![1, 2, 5]),
-            vec_of_words_to_big_int(vec![3, 4, 6]),
-        };
-        Ok(res)
-    }
-
-    fn fake_backward_batch(
-        _conn: Option<&PgConnection>,
-        _from: HashSet<Word>,
-    ) -> Result<HashSet<(Word, Word)>, anyhow::Error> {
-        // a b  | b e
-        // c d  | d f
-        let pairs = hashset! { (1,2),};
-        Ok(pairs)
-    }
-
-    fn fake_ortho_by_origin_batch(
-        _conn: Option<&PgConnection>,
-        _o: HashSet<Word>,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
-        let os = vec![Ortho::new(2, 5, 4, 6)];
-
-        Ok(os)
-    }
-
-    fn fake_ortho_by_origin_two_batch(
-        _conn: Option<&PgConnection>,
-        _o: HashSet<Word>,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
-        let os = vec![Ortho::new(1, 2, 3, 4)];
-        Ok(os)
+    use maplit::btreemap;
+
+    // a b  | b e
+    // c d  | d f
+    fn store() -> InMemoryFactStore {
+        InMemoryFactStore::from_facts(
+            vec![Ortho::new(1, 2, 3, 4), Ortho::new(2, 5, 4, 6)],
+            vec![(1, 2), (1, 3), (2, 4), (2, 5), (3, 4), (4, 6), (5, 6)],
+            vec![vec![1, 2, 5], vec![3, 4, 6]],
+        )
     }
 
     #[test]
     fn it_creates_over_when_origin_points_to_origin_from_left() {
-        // a b  | b e
-        // c d  | d f
-
         let left_ortho = Ortho::new(1, 2, 3, 4);
 
         let right_ortho = Ortho::new(2, 5, 4, 6);
 
-        let actual = over_forward(
-            None,
-            left_ortho.clone(),
-            fake_ortho_by_origin_batch,
-            fake_forward_batch,
-            fake_get_phrases_with_matching_hashes,
-            fake_phrase_exists_db_filter_head,
-        )
-        .unwrap();
+        let actual = over_forward(&store(), left_ortho.clone()).unwrap();
 
         let expected = Ortho::zip_over(
             &left_ortho,
@@ -402,22 +327,11 @@ mod tests {
 
     #[test]
     fn it_creates_over_when_origin_points_to_origin_from_right() {
-        // a b  | b e
-        // c d  | d f
-
         let left_ortho = Ortho::new(1, 2, 3, 4);
 
         let right_ortho = Ortho::new(2, 5, 4, 6);
 
-        let actual = over_back(
-            None,
-            right_ortho.clone(),
-            fake_ortho_by_origin_two_batch,
-            fake_backward_batch,
-            fake_get_phrases_with_matching_hashes,
-            fake_phrase_exists_db_filter_head,
-        )
-        .unwrap();
+        let actual = over_back(&store(), right_ortho.clone()).unwrap();
 
         let expected = Ortho::zip_over(
             &left_ortho,
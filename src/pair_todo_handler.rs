@@ -1,14 +1,11 @@
-use std::{
-    collections::{hash_map::DefaultHasher, HashSet},
-    hash::{Hash, Hasher},
-};
+use std::collections::HashSet;
 
 use crate::{
     create_todo_entry,
     diesel::query_dsl::filter_dsl::FilterDsl,
-    get_hashes_and_words_of_pairs_with_words_in,
     models::{NewOrthotope, NewTodo},
     schema::pairs::{dsl::pairs, id},
+    todo_domain::TodoDomain,
     up_handler, Word,
 };
 use crate::{
@@ -16,152 +13,131 @@ use crate::{
     models::Todo,
     schema,
 };
-use crate::{insert_orthotopes, models::ExNihilo, ortho::Ortho};
+use crate::{insert_orthotopes, models::ExNihilo, ortho::Ortho, stable_hasher::StableHasher};
 use diesel::{sql_query, PgConnection};
 
-#[tracing::instrument(level = "info", skip(pool))]
-pub fn handle_pair_todo_up_by_origin(todo: Todo, pool: diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<PgConnection>>) -> Result<(), anyhow::Error> {
-    let conn = pool.get()?;
-    conn.build_transaction().serializable().run(|| {
-        let pair = get_pair(&conn, todo.other)?;
-        let new_orthos = new_orthotopes_up_by_origin(&conn, pair)?;
-        let inserted_orthos = insert_orthotopes(&conn, HashSet::from_iter(new_orthos))?;
-        let todos: Vec<NewTodo> = inserted_orthos
-            .iter()
-            .map(|s| NewTodo {
-                domain: "orthotopes".to_owned(),
-                other: s.id,
-            })
-            .collect();
-        create_todo_entry(&conn, todos)?;
-        Ok(())
-    })
+#[tracing::instrument(level = "info", skip(conn))]
+pub fn handle_pair_todo_up_by_origin(todo: &Todo, conn: &PgConnection) -> Result<(), anyhow::Error> {
+    let pair = get_pair(conn, todo.other)?;
+    let new_orthos = new_orthotopes_up_by_origin(conn, pair)?;
+    let inserted_orthos = insert_orthotopes(conn, HashSet::from_iter(new_orthos))?;
+    let todos: Vec<NewTodo> = inserted_orthos
+        .iter()
+        .map(|s| NewTodo {
+            domain: TodoDomain::Orthotopes,
+            other: s.id,
+        })
+        .collect();
+    create_todo_entry(conn, todos)?;
+    Ok(())
 }
 
-#[tracing::instrument(level = "info", skip(pool))]
-pub fn handle_pair_todo_up_by_contents(todo: Todo, pool: diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<PgConnection>>) -> Result<(), anyhow::Error> {
-    let conn = pool.get()?;
-    conn.build_transaction().serializable().run(|| {
-        let pair = get_pair(&conn, todo.other)?;
-        let new_orthos = new_orthotopes_up_by_contents(&conn, pair)?;
-        let inserted_orthos = insert_orthotopes(&conn, HashSet::from_iter(new_orthos))?;
-        let todos: Vec<NewTodo> = inserted_orthos
-            .iter()
-            .map(|s| NewTodo {
-                domain: "orthotopes".to_owned(),
-                other: s.id,
-            })
-            .collect();
-        create_todo_entry(&conn, todos)?;
-        Ok(())
-    })
+#[tracing::instrument(level = "info", skip(conn))]
+pub fn handle_pair_todo_up_by_contents(todo: &Todo, conn: &PgConnection) -> Result<(), anyhow::Error> {
+    let pair = get_pair(conn, todo.other)?;
+    let new_orthos = new_orthotopes_up_by_contents(conn, pair)?;
+    let inserted_orthos = insert_orthotopes(conn, HashSet::from_iter(new_orthos))?;
+    let todos: Vec<NewTodo> = inserted_orthos
+        .iter()
+        .map(|s| NewTodo {
+            domain: TodoDomain::Orthotopes,
+            other: s.id,
+        })
+        .collect();
+    create_todo_entry(conn, todos)?;
+    Ok(())
 }
 
-#[tracing::instrument(level = "info", skip(pool))]
-pub fn handle_pair_todo_up_by_hop(todo: Todo, pool: diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<PgConnection>>) -> Result<(), anyhow::Error> {
-    let conn = pool.get()?;
-    conn.build_transaction().serializable().run(|| {
-        let pair = get_pair(&conn, todo.other)?;
-        let new_orthos = new_orthotopes_up_by_hop(&conn, pair)?;
-        let inserted_orthos = insert_orthotopes(&conn, HashSet::from_iter(new_orthos))?;
-        let todos: Vec<NewTodo> = inserted_orthos
-            .iter()
-            .map(|s| NewTodo {
-                domain: "orthotopes".to_owned(),
-                other: s.id,
-            })
-            .collect();
-        create_todo_entry(&conn, todos)?;
-        Ok(())
-    })
+#[tracing::instrument(level = "info", skip(conn))]
+pub fn handle_pair_todo_up_by_hop(todo: &Todo, conn: &PgConnection) -> Result<(), anyhow::Error> {
+    let pair = get_pair(conn, todo.other)?;
+    let new_orthos = new_orthotopes_up_by_hop(conn, pair)?;
+    let inserted_orthos = insert_orthotopes(conn, HashSet::from_iter(new_orthos))?;
+    let todos: Vec<NewTodo> = inserted_orthos
+        .iter()
+        .map(|s| NewTodo {
+            domain: TodoDomain::Orthotopes,
+            other: s.id,
+        })
+        .collect();
+    create_todo_entry(conn, todos)?;
+    Ok(())
 }
 
-#[tracing::instrument(level = "info", skip(pool))]
-pub fn handle_pair_todo_ffbb(todo: Todo, pool: diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<PgConnection>>) -> Result<(), anyhow::Error> {
-    let conn = pool.get()?;
-    conn.build_transaction().serializable().run(|| {
-        let pair = get_pair(&conn, todo.other)?;
-        let new_orthos = new_orthotopes_ffbb(&conn, pair)?;
-        let inserted_orthos = insert_orthotopes(&conn, HashSet::from_iter(new_orthos))?;
-        let todos: Vec<NewTodo> = inserted_orthos
-            .iter()
-            .map(|s| NewTodo {
-                domain: "orthotopes".to_owned(),
-                other: s.id,
-            })
-            .collect();
-        create_todo_entry(&conn, todos)?;
-        Ok(())
-    })
+#[tracing::instrument(level = "info", skip(conn))]
+pub fn handle_pair_todo_ffbb(todo: &Todo, conn: &PgConnection) -> Result<(), anyhow::Error> {
+    let pair = get_pair(conn, todo.other)?;
+    let new_orthos = new_orthotopes_ffbb(conn, pair)?;
+    let inserted_orthos = insert_orthotopes(conn, HashSet::from_iter(new_orthos))?;
+    let todos: Vec<NewTodo> = inserted_orthos
+        .iter()
+        .map(|s| NewTodo {
+            domain: TodoDomain::Orthotopes,
+            other: s.id,
+        })
+        .collect();
+    create_todo_entry(conn, todos)?;
+    Ok(())
 }
 
-#[tracing::instrument(level = "info", skip(pool))]
-pub fn handle_pair_todo_fbbf(todo: Todo, pool: diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<PgConnection>>) -> Result<(), anyhow::Error> {
-    let conn = pool.get()?;
-    conn.build_transaction().serializable().run(|| {
-        let pair = get_pair(&conn, todo.other)?;
-        let new_orthos = new_orthotopes_fbbf(&conn, pair)?;
-        let inserted_orthos = insert_orthotopes(&conn, HashSet::from_iter(new_orthos))?;
-        let todos: Vec<NewTodo> = inserted_orthos
-            .iter()
-            .map(|s| NewTodo {
-                domain: "orthotopes".to_owned(),
-                other: s.id,
-            })
-            .collect();
-        create_todo_entry(&conn, todos)?;
-        Ok(())
-    })
+#[tracing::instrument(level = "info", skip(conn))]
+pub fn handle_pair_todo_fbbf(todo: &Todo, conn: &PgConnection) -> Result<(), anyhow::Error> {
+    let pair = get_pair(conn, todo.other)?;
+    let new_orthos = new_orthotopes_fbbf(conn, pair)?;
+    let inserted_orthos = insert_orthotopes(conn, HashSet::from_iter(new_orthos))?;
+    let todos: Vec<NewTodo> = inserted_orthos
+        .iter()
+        .map(|s| NewTodo {
+            domain: TodoDomain::Orthotopes,
+            other: s.id,
+        })
+        .collect();
+    create_todo_entry(conn, todos)?;
+    Ok(())
 }
 
-#[tracing::instrument(level = "info", skip(pool))]
-pub fn handle_pair_todo_up(todo: Todo, pool: diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<PgConnection>>) -> Result<(), anyhow::Error> {
-    let conn = pool.get()?;
-    conn.build_transaction().serializable().run(|| {
-        let new_todos = vec![
-            NewTodo {
-                domain: "up_by_origin".to_string(),
-                other: todo.other,
-            },
-            NewTodo {
-                domain: "up_by_hop".to_string(),
-                other: todo.other,
-            },
-            NewTodo {
-                domain: "up_by_contents".to_string(),
-                other: todo.other,
-            },
-        ];
-        create_todo_entry(&conn, new_todos)?;
-        Ok(())
-    })
+#[tracing::instrument(level = "info", skip(conn))]
+pub fn handle_pair_todo_up(todo: &Todo, conn: &PgConnection) -> Result<(), anyhow::Error> {
+    let new_todos = vec![
+        NewTodo {
+            domain: TodoDomain::UpByOrigin,
+            other: todo.other,
+        },
+        NewTodo {
+            domain: TodoDomain::UpByHop,
+            other: todo.other,
+        },
+        NewTodo {
+            domain: TodoDomain::UpByContents,
+            other: todo.other,
+        },
+    ];
+    create_todo_entry(conn, new_todos)?;
+    Ok(())
 }
 
-#[tracing::instrument(level = "info", skip(pool))]
-pub fn handle_pair_todo(todo: Todo, pool: diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<PgConnection>>) -> Result<(), anyhow::Error> {
-    let conn = pool.get()?;
-    conn.build_transaction().serializable().run(|| {
-        let new_todos = vec![
-            NewTodo {
-                domain: "ex_nihilo_ffbb".to_string(),
-                other: todo.other,
-            },
-            NewTodo {
-                domain: "ex_nihilo_fbbf".to_string(),
-                other: todo.other,
-            },
-            NewTodo {
-                domain: "pair_up".to_string(),
-                other: todo.other,
-            },
-        ];
-        create_todo_entry(&conn, new_todos)?;
-        Ok(())
-    })
+#[tracing::instrument(level = "info", skip(conn))]
+pub fn handle_pair_todo(todo: &Todo, conn: &PgConnection) -> Result<(), anyhow::Error> {
+    let new_todos = vec![
+        NewTodo {
+            domain: TodoDomain::ExNihiloFfbb,
+            other: todo.other,
+        },
+        NewTodo {
+            domain: TodoDomain::ExNihiloFbbf,
+            other: todo.other,
+        },
+        NewTodo {
+            domain: TodoDomain::PairUp,
+            other: todo.other,
+        },
+    ];
+    create_todo_entry(conn, new_todos)?;
+    Ok(())
 }
 
 #[tracing::instrument(level = "info", skip(conn))]
-fn single_ffbb(
+pub(crate) fn single_ffbb(
     conn: &PgConnection,
     first: Word,
     second: Word,
@@ -193,7 +169,7 @@ fn single_ffbb(
 }
 
 #[tracing::instrument(level = "info", skip(conn))]
-fn single_fbbf(
+pub(crate) fn single_fbbf(
     conn: &PgConnection,
     first: Word,
     second: Word,
@@ -231,11 +207,9 @@ fn new_orthotopes_up_by_origin(
     pair: (Word, Word),
 ) -> Result<Vec<NewOrthotope>, anyhow::Error> {
     let up_orthos = up_handler::up_by_origin(
-        Some(conn),
+        conn,
         pair.0,
         pair.1,
-        crate::get_base_ortho_by_origin,
-        get_hashes_and_words_of_pairs_with_words_in,
     )?;
     let up_iter = up_orthos.iter();
 
@@ -249,11 +223,9 @@ fn new_orthotopes_up_by_hop(
     pair: (Word, Word),
 ) -> Result<Vec<NewOrthotope>, anyhow::Error> {
     let up_orthos = up_handler::up_by_hop(
-        Some(conn),
+        conn,
         pair.0,
         pair.1,
-        crate::get_base_ortho_by_hop,
-        get_hashes_and_words_of_pairs_with_words_in,
     )?;
     let up_iter = up_orthos.iter();
 
@@ -267,11 +239,9 @@ fn new_orthotopes_up_by_contents(
     pair: (Word, Word),
 ) -> Result<Vec<NewOrthotope>, anyhow::Error> {
     let up_orthos = up_handler::up_by_contents(
-        Some(conn),
+        conn,
         pair.0,
         pair.1,
-        crate::get_base_ortho_by_contents,
-        get_hashes_and_words_of_pairs_with_words_in,
     )?;
     let up_iter = up_orthos.iter();
 
@@ -305,9 +275,9 @@ fn new_orthotopes_fbbf(
 }
 
 pub fn data_vec_to_signed_int(x: &[u8]) -> i64 {
-    let mut hasher = DefaultHasher::new();
-    x.hash(&mut hasher);
-    hasher.finish() as i64
+    let mut hasher = StableHasher::new();
+    hasher.write_bytes(x);
+    hasher.finish()
 }
 
 #[tracing::instrument(level = "info", skip(conn))]
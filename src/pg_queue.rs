@@ -0,0 +1,259 @@
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+
+use diesel::{
+    result::DatabaseErrorKind,
+    sql_query,
+    sql_types::{BigInt, Integer},
+    ExpressionMethods, OptionalExtension, PgConnection, QueryDsl, RunQueryDsl,
+};
+use postgres::{fallible_iterator::FallibleIterator, Client, NoTls};
+
+use crate::{
+    establish_connection_safe,
+    models::Todo,
+    queue::{FailureAction, RetryPolicy},
+    schema::{todo_claims, todos},
+    shutdown::{Shutdown, SHUTDOWN_CHECK_INTERVAL},
+    todo_domain::TodoDomain,
+    worker_helper,
+};
+
+// Fired by the trigger in migrations/2026-10-18-140000_notify_on_todo_insert.
+const TODOS_CHANNEL: &str = "todos_inserted";
+
+// How long a claimed todo is hidden from other workers. A worker that dies mid-todo gives it up
+// once this runs out.
+const CLAIM_LEASE_MS: i64 = 10 * 60 * 1000;
+
+// A dedicated connection that sleeps until todos are inserted.
+pub struct TodoListener {
+    client: Client,
+}
+
+impl TodoListener {
+    pub fn connect(database_url: &str) -> Result<TodoListener, postgres::Error> {
+        let mut client = Client::connect(database_url, NoTls)?;
+        client.batch_execute(&format!("LISTEN {}", TODOS_CHANNEL))?;
+        Ok(TodoListener { client })
+    }
+
+    // Returns once a notification arrives, the poll interval passes or shutdown is requested.
+    // Notifications that arrived since the last call are already buffered, so none are lost.
+    pub fn wait(
+        &mut self,
+        poll_interval: Duration,
+        shutdown: &Shutdown,
+    ) -> Result<(), postgres::Error> {
+        let deadline = Instant::now() + poll_interval;
+        let mut notifications = self.client.notifications();
+        loop {
+            let now = Instant::now();
+            if shutdown.requested() || now >= deadline {
+                return Ok(());
+            }
+            let timeout = (deadline - now).min(SHUTDOWN_CHECK_INTERVAL);
+            if notifications.timeout_iter(timeout).next()?.is_some() {
+                while notifications.iter().next()?.is_some() {}
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WorkerConfig {
+    pub retry_policy: RetryPolicy,
+    pub poll_interval: Duration,
+}
+
+impl WorkerConfig {
+    pub fn from_env() -> Result<WorkerConfig, anyhow::Error> {
+        Ok(WorkerConfig {
+            retry_policy: RetryPolicy::from_env()?,
+            poll_interval: Duration::from_millis(
+                env::var("WORKER_POLL_INTERVAL_MS")
+                    .unwrap_or_else(|_| "5000".to_string())
+                    .parse()?,
+            ),
+        })
+    }
+}
+
+// Claims todos straight from the outbox until a connection fails or shutdown is requested. No
+// relay or RabbitMQ is needed.
+pub fn claim_forever(config: WorkerConfig, shutdown: &Shutdown) -> Result<(), anyhow::Error> {
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let conn = establish_connection_safe()?;
+    let mut listener = TodoListener::connect(&database_url)?;
+
+    while !shutdown.requested() {
+        match process_next(&conn, &config.retry_policy)? {
+            Processed::Idle => listener.wait(config.poll_interval, shutdown)?,
+            Processed::Contended => {}
+            Processed::Handled(todo) => println!("todo: {:?}", &todo),
+            Processed::Failed {
+                todo,
+                action,
+                error,
+            } => println!("{:?} for {:?} because of {error}", action, todo),
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum Processed {
+    Idle,
+    // Another worker's transaction got in the way. The todo is immediately claimable again.
+    Contended,
+    Handled(Todo),
+    Failed {
+        todo: Todo,
+        action: FailureAction,
+        error: String,
+    },
+}
+
+// The highest priority todo is leased in its own short statement, so that picking it does not make
+// the handler's serializable transaction depend on every other todo. That transaction then
+// deletes the todo alongside the handler's writes, so derived facts and the todo's consumption
+// commit together, and a todo handled twice after a lease ran out is only committed once.
+pub fn process_next(conn: &PgConnection, policy: &RetryPolicy) -> Result<Processed, anyhow::Error> {
+    let todo = match claim_next(conn, policy.max_attempts)? {
+        Some(todo) => todo,
+        None => return Ok(Processed::Idle),
+    };
+
+    let result: Result<bool, anyhow::Error> = conn.build_transaction().serializable().run(|| {
+        let deleted = diesel::delete(todos::table.find(todo.id)).execute(conn)?;
+        if deleted == 1 {
+            worker_helper::apply_todo(&todo, conn)?;
+        }
+        diesel::delete(todo_claims::table.find(todo.id)).execute(conn)?;
+        Ok(deleted == 1)
+    });
+
+    match result {
+        Ok(true) => Ok(Processed::Handled(todo)),
+        Ok(false) => Ok(Processed::Contended),
+        Err(e) if is_serialization_failure(&e) => {
+            release_claim(conn, &todo)?;
+            Ok(Processed::Contended)
+        }
+        Err(e) => {
+            let error = e.to_string();
+            let action = record_failure(conn, &todo, &error, policy)?;
+            Ok(Processed::Failed {
+                todo,
+                action,
+                error,
+            })
+        }
+    }
+}
+
+fn is_serialization_failure(e: &anyhow::Error) -> bool {
+    matches!(
+        e.downcast_ref::<diesel::result::Error>(),
+        Some(diesel::result::Error::DatabaseError(
+            DatabaseErrorKind::SerializationFailure,
+            _
+        ))
+    )
+}
+
+fn claim_next(
+    conn: &PgConnection,
+    max_attempts: u32,
+) -> Result<Option<Todo>, diesel::result::Error> {
+    let query = format!(
+        "WITH next AS (
+            SELECT todos.id, todos.domain, todos.other
+            FROM todos
+            LEFT JOIN todo_claims ON todo_claims.todo_id = todos.id
+            WHERE todo_claims.todo_id IS NULL
+            OR (todo_claims.attempts < $1 AND todo_claims.available_at <= now())
+            ORDER BY {} DESC, todos.id
+            LIMIT 1
+            FOR UPDATE OF todos SKIP LOCKED
+        ), leased AS (
+            INSERT INTO todo_claims (todo_id, available_at)
+            SELECT id, now() + $2 * interval '1 millisecond' FROM next
+            ON CONFLICT (todo_id) DO UPDATE SET available_at = excluded.available_at
+            RETURNING todo_id
+        )
+        SELECT next.id, next.domain, next.other FROM next
+        INNER JOIN leased ON leased.todo_id = next.id",
+        priority_expression()
+    );
+    sql_query(query)
+        .bind::<Integer, _>(max_attempts as i32)
+        .bind::<BigInt, _>(CLAIM_LEASE_MS)
+        .get_result(conn)
+        .optional()
+}
+
+// The same priorities the relay stamps on RabbitMQ messages.
+fn priority_expression() -> String {
+    let arms: Vec<String> = TodoDomain::ALL
+        .iter()
+        .map(|d| format!("WHEN '{}' THEN {}", d.as_str(), d.priority()))
+        .collect();
+    format!("CASE todos.domain {} END", arms.join(" "))
+}
+
+fn release_claim(conn: &PgConnection, todo: &Todo) -> Result<(), diesel::result::Error> {
+    diesel::update(todo_claims::table.find(todo.id))
+        .set(todo_claims::available_at.eq(diesel::dsl::now))
+        .execute(conn)?;
+    Ok(())
+}
+
+// The claim is still leased while this runs, so no other worker touches the row in between.
+fn record_failure(
+    conn: &PgConnection,
+    todo: &Todo,
+    error: &str,
+    policy: &RetryPolicy,
+) -> Result<FailureAction, diesel::result::Error> {
+    let attempts: i32 = diesel::update(todo_claims::table.find(todo.id))
+        .set((
+            todo_claims::attempts.eq(todo_claims::attempts + 1),
+            todo_claims::last_error.eq(error),
+        ))
+        .returning(todo_claims::attempts)
+        .get_result(conn)?;
+
+    let action = policy.on_failure(attempts as u32);
+    if let FailureAction::Retry { delay_ms } = action {
+        sql_query(
+            "UPDATE todo_claims SET available_at = now() + $2 * interval '1 millisecond'
+            WHERE todo_id = $1",
+        )
+        .bind::<Integer, _>(todo.id)
+        .bind::<BigInt, _>(delay_ms as i64)
+        .execute(conn)?;
+    }
+    Ok(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pg_queue::priority_expression;
+    use crate::todo_domain::TodoDomain;
+
+    #[test]
+    fn every_domain_is_ordered_by_its_priority() {
+        let expression = priority_expression();
+        for domain in TodoDomain::ALL {
+            assert!(expression.contains(&format!(
+                "WHEN '{}' THEN {} ",
+                domain.as_str(),
+                domain.priority()
+            )));
+        }
+    }
+}
@@ -1,17 +1,12 @@
 use std::collections::{BTreeMap, HashSet};
 
-use diesel::PgConnection;
 use itertools::{zip, Itertools};
 
-use crate::{
-    ortho::Ortho, vec_of_words_to_big_int, FailableWordToOrthoVec, FailableWordVecToOrthoVec, Word, FailableHashsetsWordsToHashsetWords,
-};
+use crate::{fact_store::FactStore, ortho::Ortho, vec_of_words_to_big_int, Word};
 
 pub(crate) fn over_by_origin(
-    conn: Option<&PgConnection>,
+    store: &impl FactStore,
     phrase: Vec<Word>,
-    ortho_by_origin: FailableWordToOrthoVec,
-    phrase_exists_db_filter: FailableHashsetsWordsToHashsetWords,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
     let lhs_phrase_head = &phrase[..phrase.len() - 1];
     let rhs_phrase_head = &phrase[1..];
@@ -19,18 +14,18 @@ pub(crate) fn over_by_origin(
     let shift_left = phrase[1];
     let shift_right = phrase[2];
 
-    let orthos_by_origin_left = ortho_by_origin(conn, head)?;
+    let orthos_by_origin_left = store.get_ortho_by_origin(head)?;
     let lhs_by_origin = orthos_by_origin_left
         .into_iter()
         .filter(|o| o.origin_has_full_length_phrase(lhs_phrase_head));
-    let orthos_by_origin_right = ortho_by_origin(conn, shift_left)?;
+    let orthos_by_origin_right = store.get_ortho_by_origin(shift_left)?;
 
     let rhs_by_origin = orthos_by_origin_right
         .into_iter()
         .filter(|o| o.origin_has_full_length_phrase(rhs_phrase_head));
 
     if lhs_by_origin.clone().next().is_none() || rhs_by_origin.clone().next().is_none() {
-        return Ok(vec![])
+        return Ok(vec![]);
     }
 
     let all_phrase_heads_left: HashSet<i64> = lhs_by_origin
@@ -55,7 +50,8 @@ pub(crate) fn over_by_origin(
         })
         .collect();
 
-    let all_phrases = phrase_exists_db_filter(conn, all_phrase_heads_left, all_phrase_heads_right)?;
+    let all_phrases =
+        store.phrase_exists_db_filter(all_phrase_heads_left, all_phrase_heads_right)?;
 
     let left_map = Itertools::into_group_map_by(lhs_by_origin.clone(), |o| o.get_dims());
     let right_map = Itertools::into_group_map_by(rhs_by_origin.clone(), |o| o.get_dims());
@@ -89,26 +85,24 @@ pub(crate) fn over_by_origin(
 }
 
 pub(crate) fn over_by_hop(
-    conn: Option<&PgConnection>,
+    store: &impl FactStore,
     phrase: Vec<Word>,
-    ortho_by_hop: FailableWordVecToOrthoVec,
-    phrase_exists_db_filter: FailableHashsetsWordsToHashsetWords,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
     let lhs_phrase_head = &phrase[..phrase.len() - 1];
     let rhs_phrase_head = &phrase[1..];
 
-    let orthos_by_hop_left = ortho_by_hop(conn, vec![phrase[0]])?;
+    let orthos_by_hop_left = store.get_ortho_by_hop(vec![phrase[0]])?;
     let lhs_by_hop = orthos_by_hop_left
         .iter()
         .filter(|o| o.hop_has_full_length_phrase(lhs_phrase_head));
 
-    let orthos_by_hop_right = ortho_by_hop(conn, vec![phrase[1]])?;
+    let orthos_by_hop_right = store.get_ortho_by_hop(vec![phrase[1]])?;
     let rhs_by_hop = orthos_by_hop_right
         .iter()
         .filter(|o| o.hop_has_full_length_phrase(rhs_phrase_head));
 
     if lhs_by_hop.clone().next().is_none() || rhs_by_hop.clone().next().is_none() {
-        return Ok(vec![])
+        return Ok(vec![]);
     }
 
     let all_phrase_heads_left: HashSet<i64> = lhs_by_hop
@@ -141,7 +135,8 @@ pub(crate) fn over_by_hop(
     let dims_left: HashSet<&BTreeMap<usize, usize>> = HashSet::from_iter(left_map.keys());
     let dims_right = HashSet::from_iter(right_map.keys());
 
-    let all_phrases = phrase_exists_db_filter(conn, all_phrase_heads_left, all_phrase_heads_right)?;
+    let all_phrases =
+        store.phrase_exists_db_filter(all_phrase_heads_left, all_phrase_heads_right)?;
 
     Ok(dims_left
         .intersection(&dims_right)
@@ -175,26 +170,24 @@ pub(crate) fn over_by_hop(
 }
 
 pub(crate) fn over_by_contents(
-    conn: Option<&PgConnection>,
+    store: &impl FactStore,
     phrase: Vec<Word>,
-    ortho_by_contents: FailableWordVecToOrthoVec,
-    phrase_exists_db_filter: FailableHashsetsWordsToHashsetWords,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
     let lhs_phrase_head = &phrase[..phrase.len() - 1];
     let rhs_phrase_head = &phrase[1..];
 
-    let orthos_by_contents_left = ortho_by_contents(conn, vec![phrase[0]])?;
+    let orthos_by_contents_left = store.get_ortho_by_contents(vec![phrase[0]])?;
     let lhs_by_contents = orthos_by_contents_left
         .iter()
         .filter(|o| o.contents_has_full_length_phrase(lhs_phrase_head));
 
-    let orthos_by_contents_right = ortho_by_contents(conn, vec![phrase[1]])?;
+    let orthos_by_contents_right = store.get_ortho_by_contents(vec![phrase[1]])?;
     let rhs_by_contents = orthos_by_contents_right
         .iter()
         .filter(|o| o.contents_has_full_length_phrase(rhs_phrase_head));
 
     if lhs_by_contents.clone().next().is_none() || rhs_by_contents.clone().next().is_none() {
-        return Ok(vec![])
+        return Ok(vec![]);
     }
 
     let all_phrase_heads_left: HashSet<i64> = lhs_by_contents
@@ -235,7 +228,8 @@ pub(crate) fn over_by_contents(
     let dims_left: HashSet<&BTreeMap<usize, usize>> = HashSet::from_iter(left_map.keys());
     let dims_right = HashSet::from_iter(right_map.keys());
 
-    let all_phrases = phrase_exists_db_filter(conn, all_phrase_heads_left, all_phrase_heads_right)?;
+    let all_phrases =
+        store.phrase_exists_db_filter(all_phrase_heads_left, all_phrase_heads_right)?;
 
     Ok(dims_left
         .intersection(&dims_right)
@@ -382,74 +376,30 @@ fn make_mapping(
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
-
-    use diesel::PgConnection;
-    use maplit::{btreemap, hashset};
+    use maplit::btreemap;
 
     use crate::{
+        fact_store::InMemoryFactStore,
         ortho::Ortho,
         phrase_ortho_handler::{over_by_contents, over_by_hop, over_by_origin},
-        vec_of_words_to_big_int, Word,
+        Word,
     };
 
     use super::axis_lengths_match;
 
-    pub(crate) fn fake_phrase_exists_db_filter(
-        _conn: Option<&PgConnection>,
-        _left: HashSet<i64>,
-        _right: HashSet<i64>,
-    ) -> Result<HashSet<i64>, anyhow::Error> {
-        Ok(hashset! {
-            vec_of_words_to_big_int(vec![1, 2, 5]),
-            vec_of_words_to_big_int(vec![3, 4, 6]),
-        })
-    }
-
-    pub(crate) fn fake_phrase_exists_db_filter_two(
-        _conn: Option<&PgConnection>,
-        _left: HashSet<i64>,
-        _right: HashSet<i64>,
-    ) -> Result<HashSet<i64>, anyhow::Error> {
-        Ok(hashset! {
-            vec_of_words_to_big_int(vec![1, 2, 5])
-        })
+    fn store(orthos: Vec<Ortho>, phrases: Vec<Vec<Word>>) -> InMemoryFactStore {
+        InMemoryFactStore::from_facts(orthos, vec![], phrases)
     }
 
-    pub(crate) fn fake_phrase_exists_db_filter_three(
-        _conn: Option<&PgConnection>,
-        _left: HashSet<i64>,
-        _right: HashSet<i64>,
-    ) -> Result<HashSet<i64>, anyhow::Error> {
-        Ok(hashset! {
-            vec_of_words_to_big_int(vec![1, 4, 7]),
-            vec_of_words_to_big_int(vec![2, 5, 8]),
-            vec_of_words_to_big_int(vec![3, 6, 9]),
-        })
+    fn phrases() -> Vec<Vec<Word>> {
+        vec![vec![1, 2, 5], vec![3, 4, 6]]
     }
 
-    fn fake_ortho_by_origin(
-        _conn: Option<&PgConnection>,
-        o: Word,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
-        let mut pairs = btreemap! { 1 => vec![Ortho::new(
-            1,
-            2,
-            3,
-            4,
-        )], 2 => vec![Ortho::new(
-            2,
-            5,
-            4,
-            6,
-        )]};
-        Ok(pairs.entry(o).or_default().to_owned())
+    fn orthos_by_origin() -> Vec<Ortho> {
+        vec![Ortho::new(1, 2, 3, 4), Ortho::new(2, 5, 4, 6)]
     }
 
-    fn fake_ortho_by_origin_two(
-        _conn: Option<&PgConnection>,
-        o: Word,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
+    fn orthos_by_origin_two() -> Vec<Ortho> {
         let small = Ortho::new(2, 4, 5, 25);
 
         // a b e
@@ -471,14 +421,10 @@ mod tests {
             5,
         );
 
-        let mut pairs = btreemap! { 1 => vec![bigger], 2 => vec![small]};
-        Ok(pairs.entry(o).or_default().to_owned())
+        vec![bigger, small]
     }
 
-    fn fake_ortho_by_origin_three(
-        _conn: Option<&PgConnection>,
-        o: Word,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
+    fn orthos_by_origin_three() -> Vec<Ortho> {
         let l = Ortho::zip_over(
             &Ortho::new(1, 2, 3, 4),
             &Ortho::new(2, 5, 4, 6),
@@ -502,14 +448,10 @@ mod tests {
             7,
         );
 
-        let mut pairs = btreemap! { 1 => vec![l], 2 => vec![r]};
-        Ok(pairs.entry(o).or_default().to_owned())
+        vec![l, r]
     }
 
-    fn fake_ortho_by_origin_four(
-        _conn: Option<&PgConnection>,
-        o: Word,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
+    fn orthos_by_origin_four() -> Vec<Ortho> {
         // a b e   b e b
         // c d f   d f e
         // h i j   i j g
@@ -608,16 +550,10 @@ mod tests {
             9,
         );
 
-        let mut pairs = btreemap! { 1 => vec![abecdfhij], 2 => vec![bebdfeijg]};
-        Ok(pairs.entry(o).or_default().to_owned())
+        vec![abecdfhij, bebdfeijg]
     }
 
-    fn fake_ortho_by_contents(
-        _conn: Option<&PgConnection>,
-        o: Vec<Word>,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
-        let mut ans = vec![];
-
+    fn orthos_by_contents() -> Vec<Ortho> {
         // a b c
         // d e f
 
@@ -652,40 +588,7 @@ mod tests {
             6,
         );
 
-        if o.contains(&3) || o.contains(&5) || o.contains(&6) {
-            ans.push(abcdef);
-        }
-
-        if o.contains(&6) || o.contains(&8) || o.contains(&9) {
-            ans.push(defghi);
-        }
-
-        Ok(ans)
-    }
-
-    fn fake_ortho_by_hop(
-        _conn: Option<&PgConnection>,
-        o: Vec<Word>,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
-        let mut ans = vec![];
-
-        if o.contains(&2) {
-            ans.push(Ortho::new(1, 2, 3, 4))
-        }
-
-        if o.contains(&3) {
-            ans.push(Ortho::new(1, 2, 3, 4))
-        }
-
-        if o.contains(&5) {
-            ans.push(Ortho::new(2, 5, 4, 6))
-        }
-
-        if o.contains(&4) {
-            ans.push(Ortho::new(2, 5, 4, 6))
-        }
-
-        Ok(ans)
+        vec![abcdef, defghi]
     }
 
     #[test]
@@ -707,52 +610,31 @@ mod tests {
             5,
         );
 
-        let actual = over_by_origin(
-            None,
-            vec![1, 2, 5],
-            fake_ortho_by_origin,
-            fake_phrase_exists_db_filter,
-        )
-        .unwrap();
+        let actual = over_by_origin(&store(orthos_by_origin(), phrases()), vec![1, 2, 5]).unwrap();
 
         assert_eq!(vec![expected], actual);
     }
 
     #[test]
     fn over_filters_mismatched_dims() {
-        let actual = over_by_origin(
-            None,
-            vec![1, 2, 5],
-            fake_ortho_by_origin_two,
-            fake_phrase_exists_db_filter,
-        )
-        .unwrap();
+        let actual =
+            over_by_origin(&store(orthos_by_origin_two(), phrases()), vec![1, 2, 5]).unwrap();
 
         assert_eq!(actual.len(), 0);
     }
 
     #[test]
     fn over_filters_shift_axis_is_wrong_length() {
-        let actual = over_by_origin(
-            None,
-            vec![1, 2, 5],
-            fake_ortho_by_origin_three,
-            fake_phrase_exists_db_filter,
-        )
-        .unwrap();
+        let actual =
+            over_by_origin(&store(orthos_by_origin_three(), phrases()), vec![1, 2, 5]).unwrap();
 
         assert_eq!(actual.len(), 0);
     }
 
     #[test]
     fn over_filters_if_the_phrase_wont_result() {
-        let actual = over_by_origin(
-            None,
-            vec![1, 2, 5, 7],
-            fake_ortho_by_origin_four,
-            fake_phrase_exists_db_filter,
-        )
-        .unwrap();
+        let actual =
+            over_by_origin(&store(orthos_by_origin_four(), phrases()), vec![1, 2, 5, 7]).unwrap();
 
         assert_eq!(actual.len(), 0);
     }
@@ -804,10 +686,8 @@ mod tests {
     #[test]
     fn over_by_origin_filters_if_a_phrase_is_missing_from_db() {
         let actual = over_by_origin(
-            None,
+            &store(orthos_by_origin(), vec![vec![1, 2, 5]]),
             vec![1, 2, 5],
-            fake_ortho_by_origin,
-            fake_phrase_exists_db_filter_two,
         )
         .unwrap();
 
@@ -828,13 +708,7 @@ mod tests {
             5,
         );
 
-        let actual = over_by_hop(
-            None,
-            vec![3, 4, 6],
-            fake_ortho_by_hop,
-            fake_phrase_exists_db_filter,
-        )
-        .unwrap();
+        let actual = over_by_hop(&store(orthos_by_origin(), phrases()), vec![3, 4, 6]).unwrap();
 
         assert_eq!(vec![expected], actual);
     }
@@ -891,13 +765,9 @@ mod tests {
             7,
         );
 
-        let actual = over_by_contents(
-            None,
-            vec![3, 6, 9],
-            fake_ortho_by_contents,
-            fake_phrase_exists_db_filter_three,
-        )
-        .unwrap();
+        let phrases = vec![vec![1, 4, 7], vec![2, 5, 8], vec![3, 6, 9]];
+        let actual =
+            over_by_contents(&store(orthos_by_contents(), phrases), vec![3, 6, 9]).unwrap();
 
         assert_eq!(vec![expected], actual);
     }
@@ -9,6 +9,7 @@ use crate::models::Todo;
 use crate::ortho_to_orthotope;
 use crate::phrase_ortho_handler;
 use crate::schema::phrases::dsl::phrases;
+use crate::todo_domain::TodoDomain;
 use crate::Word;
 
 use crate::{
@@ -17,84 +18,72 @@ use crate::{
     schema::phrases::id,
 };
 
-#[tracing::instrument(level = "info", skip(pool))]
-pub(crate) fn handle_phrase_todo_origin(todo: crate::models::Todo, pool: diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<PgConnection>>) -> Result<(), anyhow::Error> {
-    let conn = pool.get()?;
-    conn.build_transaction().serializable().run(|| {
-        let phrase = get_phrase(&conn, todo.other)?;
-        let new_orthos = new_orthotopes_by_origin(&conn, phrase)?;
-        let inserted_orthos = insert_orthotopes(&conn, HashSet::from_iter(new_orthos))?;
-        let todos: Vec<NewTodo> = inserted_orthos
-            .iter()
-            .map(|s| NewTodo {
-                domain: "orthotopes".to_owned(),
-                other: s.id,
-            })
-            .collect();
-        create_todo_entry(&conn, todos)?;
-        Ok(())
-    })
+#[tracing::instrument(level = "info", skip(conn))]
+pub(crate) fn handle_phrase_todo_origin(todo: &Todo, conn: &PgConnection) -> Result<(), anyhow::Error> {
+    let phrase = get_phrase(conn, todo.other)?;
+    let new_orthos = new_orthotopes_by_origin(conn, phrase)?;
+    let inserted_orthos = insert_orthotopes(conn, HashSet::from_iter(new_orthos))?;
+    let todos: Vec<NewTodo> = inserted_orthos
+        .iter()
+        .map(|s| NewTodo {
+            domain: TodoDomain::Orthotopes,
+            other: s.id,
+        })
+        .collect();
+    create_todo_entry(conn, todos)?;
+    Ok(())
 }
 
-#[tracing::instrument(level = "info", skip(pool))]
-pub(crate) fn handle_phrase_todo_hop(todo: crate::models::Todo, pool: diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<PgConnection>>) -> Result<(), anyhow::Error> {
-    let conn = pool.get()?;
-    conn.build_transaction().serializable().run(|| {
-        let phrase = get_phrase(&conn, todo.other)?;
-        let new_orthos = new_orthotopes_by_hop(&conn, phrase)?;
-        let inserted_orthos = insert_orthotopes(&conn, HashSet::from_iter(new_orthos))?;
-        let todos: Vec<NewTodo> = inserted_orthos
-            .iter()
-            .map(|s| NewTodo {
-                domain: "orthotopes".to_owned(),
-                other: s.id,
-            })
-            .collect();
-        create_todo_entry(&conn, todos)?;
-        Ok(())
-    })
+#[tracing::instrument(level = "info", skip(conn))]
+pub(crate) fn handle_phrase_todo_hop(todo: &Todo, conn: &PgConnection) -> Result<(), anyhow::Error> {
+    let phrase = get_phrase(conn, todo.other)?;
+    let new_orthos = new_orthotopes_by_hop(conn, phrase)?;
+    let inserted_orthos = insert_orthotopes(conn, HashSet::from_iter(new_orthos))?;
+    let todos: Vec<NewTodo> = inserted_orthos
+        .iter()
+        .map(|s| NewTodo {
+            domain: TodoDomain::Orthotopes,
+            other: s.id,
+        })
+        .collect();
+    create_todo_entry(conn, todos)?;
+    Ok(())
 }
 
-#[tracing::instrument(level = "info", skip(pool))]
-pub(crate) fn handle_phrase_todo_contents(todo: crate::models::Todo, pool: diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<PgConnection>>) -> Result<(), anyhow::Error> {
-    let conn = pool.get()?;
-    conn.build_transaction().serializable().run(|| {
-        let phrase = get_phrase(&conn, todo.other)?;
-        let new_orthos = new_orthotopes_by_contents(&conn, phrase)?;
-        let inserted_orthos = insert_orthotopes(&conn, HashSet::from_iter(new_orthos))?;
-        let todos: Vec<NewTodo> = inserted_orthos
-            .iter()
-            .map(|s| NewTodo {
-                domain: "orthotopes".to_owned(),
-                other: s.id,
-            })
-            .collect();
-        create_todo_entry(&conn, todos)?;
-        Ok(())
-    })
+#[tracing::instrument(level = "info", skip(conn))]
+pub(crate) fn handle_phrase_todo_contents(todo: &Todo, conn: &PgConnection) -> Result<(), anyhow::Error> {
+    let phrase = get_phrase(conn, todo.other)?;
+    let new_orthos = new_orthotopes_by_contents(conn, phrase)?;
+    let inserted_orthos = insert_orthotopes(conn, HashSet::from_iter(new_orthos))?;
+    let todos: Vec<NewTodo> = inserted_orthos
+        .iter()
+        .map(|s| NewTodo {
+            domain: TodoDomain::Orthotopes,
+            other: s.id,
+        })
+        .collect();
+    create_todo_entry(conn, todos)?;
+    Ok(())
 }
 
-#[tracing::instrument(level = "info", skip(pool))]
-pub fn handle_phrase_todo(todo: Todo, pool: diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<PgConnection>>) -> Result<(), anyhow::Error> {
-    let conn = pool.get()?;
-    conn.build_transaction().serializable().run(|| {
-        let new_todos = vec![
-            NewTodo {
-                domain: "phrase_by_origin".to_string(),
-                other: todo.other,
-            },
-            NewTodo {
-                domain: "phrase_by_hop".to_string(),
-                other: todo.other,
-            },
-            NewTodo {
-                domain: "phrase_by_contents".to_string(),
-                other: todo.other,
-            },
-        ];
-        create_todo_entry(&conn, new_todos)?;
-        Ok(())
-    })
+#[tracing::instrument(level = "info", skip(conn))]
+pub fn handle_phrase_todo(todo: &Todo, conn: &PgConnection) -> Result<(), anyhow::Error> {
+    let new_todos = vec![
+        NewTodo {
+            domain: TodoDomain::PhraseByOrigin,
+            other: todo.other,
+        },
+        NewTodo {
+            domain: TodoDomain::PhraseByHop,
+            other: todo.other,
+        },
+        NewTodo {
+            domain: TodoDomain::PhraseByContents,
+            other: todo.other,
+        },
+    ];
+    create_todo_entry(conn, new_todos)?;
+    Ok(())
 }
 
 #[tracing::instrument(level = "info", skip(conn))]
@@ -103,10 +92,8 @@ fn new_orthotopes_by_origin(
     phrase: Vec<Word>,
 ) -> Result<Vec<NewOrthotope>, anyhow::Error> {
     let orthos = phrase_ortho_handler::over_by_origin(
-        Some(conn),
+        conn,
         phrase,
-        crate::get_ortho_by_origin,
-        crate::phrase_exists_db_filter,
     )?;
 
     let res = orthos.iter().map(ortho_to_orthotope).collect();
@@ -119,10 +106,8 @@ fn new_orthotopes_by_hop(
     phrase: Vec<Word>,
 ) -> Result<Vec<NewOrthotope>, anyhow::Error> {
     let orthos = phrase_ortho_handler::over_by_hop(
-        Some(conn),
+        conn,
         phrase,
-        crate::get_ortho_by_hop,
-        crate::phrase_exists_db_filter,
     )?;
 
     let res = orthos.iter().map(ortho_to_orthotope).collect();
@@ -135,10 +120,8 @@ fn new_orthotopes_by_contents(
     phrase: Vec<Word>,
 ) -> Result<Vec<NewOrthotope>, anyhow::Error> {
     let orthos = phrase_ortho_handler::over_by_contents(
-        Some(conn),
+        conn,
         phrase,
-        crate::get_ortho_by_contents,
-        crate::phrase_exists_db_filter,
     )?;
 
     let res = orthos.iter().map(ortho_to_orthotope).collect();
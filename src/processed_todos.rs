@@ -0,0 +1,43 @@
+use std::{env, time::Duration};
+
+use diesel::{sql_query, sql_types::BigInt, ExpressionMethods, PgConnection, RunQueryDsl};
+
+use crate::{models::Todo, schema::processed_todos};
+
+// Called at the start of a handler's transaction. The ledger row commits together with the
+// handler's writes, so a todo delivered twice (relay crash after publish, broker redelivery) does
+// its work once and the second delivery sees `false`.
+pub(crate) fn claim(conn: &PgConnection, todo: &Todo) -> Result<bool, diesel::result::Error> {
+    let claimed = diesel::insert_into(processed_todos::table)
+        .values(processed_todos::todo_id.eq(todo.id))
+        .on_conflict_do_nothing()
+        .execute(conn)?;
+    if claimed == 0 {
+        tracing::info!("skipping already processed todo {}", todo.id);
+    }
+    Ok(claimed == 1)
+}
+
+// Defaults to a week, far longer than a relay restart plus the longest retry backoff. It has to
+// outlast the longest time a todo can wait in the queue.
+pub(crate) fn retention_from_env() -> Duration {
+    let hours: u64 = env::var("PROCESSED_TODO_RETENTION_HOURS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(168);
+    Duration::from_secs(hours * 60 * 60)
+}
+
+// Run by the relay whenever it is idle. A duplicate is either a republished batch or a broker
+// redelivery, and both reach a worker while the todo's first message is still recent, so a row
+// older than `retention` no longer stops anything.
+pub(crate) fn prune(
+    conn: &PgConnection,
+    retention: Duration,
+) -> Result<usize, diesel::result::Error> {
+    sql_query(
+        "DELETE FROM processed_todos WHERE claimed_at < now() - $1 * interval '1 millisecond'",
+    )
+    .bind::<BigInt, _>(retention.as_millis() as i64)
+    .execute(conn)
+}
@@ -0,0 +1,600 @@
+use std::{
+    env,
+    time::{Duration, Instant},
+};
+
+use amiquip::{
+    AmqpProperties, AmqpValue, Channel, Confirm, ConfirmSmoother, Consumer, ConsumerMessage,
+    ConsumerOptions, Exchange, FieldTable, Publish, Queue, QueueDeclareOptions,
+};
+use anyhow::bail;
+use crossbeam_channel::{Receiver, RecvTimeoutError};
+use serde::Serialize;
+
+use crate::models::Todo;
+
+pub const WORK_QUEUE: &str = "work";
+pub const RETRY_QUEUE: &str = "work-retry";
+pub const DEAD_LETTER_QUEUE: &str = "work-dead";
+
+const RETRY_COUNT_HEADER: &str = "x-retry-count";
+const LAST_ERROR_HEADER: &str = "x-last-error";
+
+const CONFIRM_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub fn declare_work_queue(channel: &Channel) -> amiquip::Result<Queue<'_>> {
+    let mut arguments = FieldTable::new();
+    arguments.insert("x-max-priority".to_string(), AmqpValue::ShortInt(20));
+
+    channel.queue_declare(
+        WORK_QUEUE,
+        QueueDeclareOptions {
+            durable: true,
+            arguments,
+            ..QueueDeclareOptions::default()
+        },
+    )
+}
+
+// Retries wait out their backoff in a queue of their own delay, e.g. `work-retry-4000`, until its
+// TTL runs out and RabbitMQ dead-letters them back onto the work queue. This is how backoff is
+// implemented without blocking a worker. RabbitMQ only expires messages at the head of a queue, so
+// one queue with a per-message expiration would hold a short retry behind a long one.
+pub fn retry_queue_name(delay_ms: u64) -> String {
+    format!("{}-{}", RETRY_QUEUE, delay_ms)
+}
+
+pub fn declare_retry_queue(channel: &Channel, delay_ms: u64) -> amiquip::Result<Queue<'_>> {
+    let mut arguments = FieldTable::new();
+    arguments.insert(
+        "x-message-ttl".to_string(),
+        AmqpValue::LongLongInt(delay_ms as i64),
+    );
+    arguments.insert(
+        "x-dead-letter-exchange".to_string(),
+        AmqpValue::LongString("".to_string()),
+    );
+    arguments.insert(
+        "x-dead-letter-routing-key".to_string(),
+        AmqpValue::LongString(WORK_QUEUE.to_string()),
+    );
+
+    channel.queue_declare(
+        retry_queue_name(delay_ms),
+        QueueDeclareOptions {
+            durable: true,
+            arguments,
+            ..QueueDeclareOptions::default()
+        },
+    )
+}
+
+// The number of todos ready on the work queue. It is read with a passive declare, which neither
+// creates the queue nor needs its arguments, and fails if nothing has declared the queue yet.
+pub fn work_depth(channel: &Channel) -> amiquip::Result<u32> {
+    let queue = channel.queue_declare_passive(WORK_QUEUE)?;
+    Ok(queue
+        .declared_message_count()
+        .expect("queue must be declared non-immediate"))
+}
+
+pub fn declare_dead_letter_queue(channel: &Channel) -> amiquip::Result<Queue<'_>> {
+    channel.queue_declare(
+        DEAD_LETTER_QUEUE,
+        QueueDeclareOptions {
+            durable: true,
+            ..QueueDeclareOptions::default()
+        },
+    )
+}
+
+pub fn todo_properties(todo: &Todo) -> AmqpProperties {
+    AmqpProperties::default()
+        .with_delivery_mode(2)
+        .with_priority(todo.domain.priority())
+        .with_message_id(todo.id.to_string())
+}
+
+struct PublisherConfirms {
+    receiver: Receiver<Confirm>,
+    smoother: ConfirmSmoother,
+}
+
+impl PublisherConfirms {
+    fn enable(channel: &Channel) -> amiquip::Result<PublisherConfirms> {
+        let receiver = channel.listen_for_publisher_confirms()?;
+        channel.enable_publisher_confirms()?;
+        Ok(PublisherConfirms::new(receiver))
+    }
+
+    fn new(receiver: Receiver<Confirm>) -> PublisherConfirms {
+        PublisherConfirms {
+            receiver,
+            smoother: ConfirmSmoother::new(),
+        }
+    }
+
+    // Blocks until the broker has confirmed the next `published` messages on the channel. Any nack
+    // or a timeout is an error; the channel should be discarded afterwards because the remaining
+    // confirms no longer line up with what the caller expects.
+    fn wait_for(&mut self, published: usize, timeout: Duration) -> Result<(), anyhow::Error> {
+        let deadline = Instant::now() + timeout;
+        let mut acked = 0;
+        let mut nacked = 0;
+        while acked + nacked < published {
+            let raw = match self.receiver.recv_deadline(deadline) {
+                Ok(raw) => raw,
+                Err(e) => bail!(
+                    "{} of {} messages unconfirmed: {}",
+                    published - acked - nacked,
+                    published,
+                    e
+                ),
+            };
+            for confirm in self.smoother.process(raw) {
+                match confirm {
+                    Confirm::Ack(_) => acked += 1,
+                    Confirm::Nack(_) => nacked += 1,
+                }
+            }
+        }
+        if nacked > 0 {
+            bail!("broker rejected {} of {} messages", nacked, published);
+        }
+        Ok(())
+    }
+}
+
+pub fn retry_count(properties: &AmqpProperties) -> u32 {
+    match properties
+        .headers()
+        .as_ref()
+        .and_then(|h| h.get(RETRY_COUNT_HEADER))
+    {
+        Some(AmqpValue::LongUInt(n)) => *n,
+        _ => 0,
+    }
+}
+
+pub fn last_error(properties: &AmqpProperties) -> Option<String> {
+    match properties
+        .headers()
+        .as_ref()
+        .and_then(|h| h.get(LAST_ERROR_HEADER))
+    {
+        Some(AmqpValue::LongString(e)) => Some(e.clone()),
+        _ => None,
+    }
+}
+
+fn with_failure_headers(properties: AmqpProperties, attempts: u32, error: &str) -> AmqpProperties {
+    let mut headers = properties.headers().clone().unwrap_or_default();
+    headers.insert(
+        RETRY_COUNT_HEADER.to_string(),
+        AmqpValue::LongUInt(attempts),
+    );
+    headers.insert(
+        LAST_ERROR_HEADER.to_string(),
+        AmqpValue::LongString(error.to_string()),
+    );
+    properties.with_headers(headers)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FailureAction {
+    Retry { delay_ms: u64 },
+    DeadLetter,
+}
+
+pub fn on_failure(attempts: u32, max_attempts: u32, base_delay_ms: u64) -> FailureAction {
+    if attempts >= max_attempts {
+        FailureAction::DeadLetter
+    } else {
+        FailureAction::Retry {
+            delay_ms: backoff_ms(attempts, base_delay_ms),
+        }
+    }
+}
+
+pub fn backoff_ms(attempts: u32, base_delay_ms: u64) -> u64 {
+    base_delay_ms.saturating_mul(1u64 << attempts.saturating_sub(1).min(20))
+}
+
+pub fn publish_failure(
+    exchange: &Exchange,
+    todo_body: &[u8],
+    properties: &AmqpProperties,
+    error: &str,
+    max_attempts: u32,
+    base_delay_ms: u64,
+) -> amiquip::Result<FailureAction> {
+    let attempts = retry_count(properties) + 1;
+    let properties = with_failure_headers(properties.clone(), attempts, error);
+    let action = on_failure(attempts, max_attempts, base_delay_ms);
+    match &action {
+        FailureAction::Retry { delay_ms } => exchange.publish(Publish::with_properties(
+            todo_body,
+            retry_queue_name(*delay_ms),
+            properties,
+        ))?,
+        FailureAction::DeadLetter => exchange.publish(Publish::with_properties(
+            todo_body,
+            DEAD_LETTER_QUEUE,
+            properties,
+        ))?,
+    }
+    Ok(action)
+}
+
+pub fn publish_dead_letter(
+    exchange: &Exchange,
+    body: &[u8],
+    properties: &AmqpProperties,
+    error: &str,
+) -> amiquip::Result<()> {
+    let attempts = retry_count(properties) + 1;
+    exchange.publish(Publish::with_properties(
+        body,
+        DEAD_LETTER_QUEUE,
+        with_failure_headers(properties.clone(), attempts, error),
+    ))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadLetter {
+    pub todo: Option<Todo>,
+    pub attempts: u32,
+    pub error: Option<String>,
+}
+
+// Messages are fetched without acking, so they return to the dead letter queue once the channel
+// closes.
+pub fn list_dead_letters(channel: &Channel) -> amiquip::Result<Vec<DeadLetter>> {
+    let dead = declare_dead_letter_queue(channel)?;
+    let mut res = vec![];
+    while let Some(get) = dead.get(false)? {
+        res.push(DeadLetter {
+            todo: bincode::deserialize(&get.delivery.body).ok(),
+            attempts: retry_count(&get.delivery.properties),
+            error: last_error(&get.delivery.properties),
+        });
+    }
+    Ok(res)
+}
+
+pub fn redrive_dead_letters(channel: &Channel) -> amiquip::Result<usize> {
+    let dead = declare_dead_letter_queue(channel)?;
+    declare_work_queue(channel)?;
+    let exchange = Exchange::direct(channel);
+    let mut redriven = 0;
+    while let Some(get) = dead.get(false)? {
+        if let Ok(todo) = bincode::deserialize::<Todo>(&get.delivery.body) {
+            exchange.publish(Publish::with_properties(
+                &get.delivery.body,
+                WORK_QUEUE,
+                todo_properties(&todo),
+            ))?;
+            get.ack(channel)?;
+            redriven += 1;
+        }
+    }
+    Ok(redriven)
+}
+
+#[derive(Debug)]
+pub struct Delivery<H> {
+    pub todo: Todo,
+    pub(crate) handle: H,
+}
+
+#[derive(Debug)]
+pub enum Consumed<H> {
+    Delivery(Delivery<H>),
+    TimedOut,
+    Ended,
+}
+
+pub trait WorkQueue {
+    type Handle;
+
+    fn publish(&mut self, todos: &[Todo]) -> Result<(), anyhow::Error>;
+
+    // Blocks until a todo is available. Returns `None` once the queue stops delivering.
+    fn consume(&mut self) -> Result<Option<Delivery<Self::Handle>>, anyhow::Error>;
+
+    // Like `consume`, but gives up once `timeout` passes without a todo.
+    fn consume_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Consumed<Self::Handle>, anyhow::Error>;
+
+    fn ack(&mut self, delivery: Delivery<Self::Handle>) -> Result<(), anyhow::Error>;
+
+    // Schedules a retry or dead-letters the todo according to the queue's retry policy.
+    fn nack(
+        &mut self,
+        delivery: Delivery<Self::Handle>,
+        error: &str,
+    ) -> Result<FailureAction, anyhow::Error>;
+
+    fn depth(&mut self) -> Result<u32, anyhow::Error>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+}
+
+impl RetryPolicy {
+    pub fn from_env() -> Result<RetryPolicy, anyhow::Error> {
+        let default = RetryPolicy::default();
+        Ok(RetryPolicy {
+            max_attempts: match env::var("MAX_TODO_ATTEMPTS") {
+                Ok(s) => s.parse()?,
+                Err(_) => default.max_attempts,
+            },
+            base_delay_ms: match env::var("RETRY_BASE_DELAY_MS") {
+                Ok(s) => s.parse()?,
+                Err(_) => default.base_delay_ms,
+            },
+        })
+    }
+
+    pub fn on_failure(&self, attempts: u32) -> FailureAction {
+        on_failure(attempts, self.max_attempts, self.base_delay_ms)
+    }
+
+    // The delay of every retry this policy can make, each of which needs its retry queue.
+    pub fn retry_delays(&self) -> Vec<u64> {
+        let mut delays: Vec<u64> = (1..self.max_attempts)
+            .map(|attempts| backoff_ms(attempts, self.base_delay_ms))
+            .collect();
+        delays.dedup();
+        delays
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            base_delay_ms: 1000,
+        }
+    }
+}
+
+// Every publish on the channel, including retries, waits for a broker confirm.
+pub struct RabbitWorkQueue<'a> {
+    channel: &'a Channel,
+    queue: Queue<'a>,
+    exchange: Exchange<'a>,
+    confirms: PublisherConfirms,
+    consumer: Option<Consumer<'a>>,
+    policy: RetryPolicy,
+}
+
+impl<'a> RabbitWorkQueue<'a> {
+    pub fn new(channel: &'a Channel, policy: RetryPolicy) -> amiquip::Result<RabbitWorkQueue<'a>> {
+        let queue = declare_work_queue(channel)?;
+        for delay_ms in policy.retry_delays() {
+            declare_retry_queue(channel, delay_ms)?;
+        }
+        declare_dead_letter_queue(channel)?;
+        Ok(RabbitWorkQueue {
+            channel,
+            queue,
+            exchange: Exchange::direct(channel),
+            confirms: PublisherConfirms::enable(channel)?,
+            consumer: None,
+            policy,
+        })
+    }
+
+    fn receive(
+        &mut self,
+        deadline: Option<Instant>,
+    ) -> Result<Consumed<amiquip::Delivery>, anyhow::Error> {
+        if self.consumer.is_none() {
+            self.consumer = Some(self.queue.consume(ConsumerOptions::default())?);
+        }
+        let consumer = self.consumer.as_ref().expect("consumer was just started");
+
+        loop {
+            let message = match deadline {
+                Some(deadline) => match consumer.receiver().recv_deadline(deadline) {
+                    Ok(message) => message,
+                    Err(RecvTimeoutError::Timeout) => return Ok(Consumed::TimedOut),
+                    Err(RecvTimeoutError::Disconnected) => return Ok(Consumed::Ended),
+                },
+                None => match consumer.receiver().recv() {
+                    Ok(message) => message,
+                    Err(_) => return Ok(Consumed::Ended),
+                },
+            };
+            match message {
+                ConsumerMessage::Delivery(delivery) => match bincode::deserialize(&delivery.body) {
+                    Ok(todo) => {
+                        return Ok(Consumed::Delivery(Delivery {
+                            todo,
+                            handle: delivery,
+                        }))
+                    }
+                    Err(e) => {
+                        println!("dead-lettering undecodable message because of {e}");
+                        publish_dead_letter(
+                            &self.exchange,
+                            &delivery.body,
+                            &delivery.properties,
+                            &e.to_string(),
+                        )?;
+                        self.confirms.wait_for(1, CONFIRM_TIMEOUT)?;
+                        delivery.ack(self.channel)?;
+                    }
+                },
+                ConsumerMessage::ServerClosedChannel(e)
+                | ConsumerMessage::ServerClosedConnection(e) => return Err(e.into()),
+                other => {
+                    println!("Consumer ended: {:?}", other);
+                    return Ok(Consumed::Ended);
+                }
+            }
+        }
+    }
+}
+
+impl<'a> WorkQueue for RabbitWorkQueue<'a> {
+    type Handle = amiquip::Delivery;
+
+    fn publish(&mut self, todos: &[Todo]) -> Result<(), anyhow::Error> {
+        for todo in todos {
+            let data = bincode::serialize(&todo).expect("bincode should be able to serialize");
+            self.exchange.publish(Publish::with_properties(
+                &data,
+                WORK_QUEUE,
+                todo_properties(todo),
+            ))?;
+        }
+        self.confirms.wait_for(todos.len(), CONFIRM_TIMEOUT)
+    }
+
+    fn consume(&mut self) -> Result<Option<Delivery<Self::Handle>>, anyhow::Error> {
+        match self.receive(None)? {
+            Consumed::Delivery(delivery) => Ok(Some(delivery)),
+            Consumed::TimedOut | Consumed::Ended => Ok(None),
+        }
+    }
+
+    fn consume_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Consumed<Self::Handle>, anyhow::Error> {
+        self.receive(Some(Instant::now() + timeout))
+    }
+
+    fn ack(&mut self, delivery: Delivery<Self::Handle>) -> Result<(), anyhow::Error> {
+        Ok(delivery.handle.ack(self.channel)?)
+    }
+
+    fn nack(
+        &mut self,
+        delivery: Delivery<Self::Handle>,
+        error: &str,
+    ) -> Result<FailureAction, anyhow::Error> {
+        let action = publish_failure(
+            &self.exchange,
+            &delivery.handle.body,
+            &delivery.handle.properties,
+            error,
+            self.policy.max_attempts,
+            self.policy.base_delay_ms,
+        )?;
+        self.confirms.wait_for(1, CONFIRM_TIMEOUT)?;
+        delivery.handle.ack(self.channel)?;
+        Ok(action)
+    }
+
+    fn depth(&mut self) -> Result<u32, anyhow::Error> {
+        let queue = declare_work_queue(self.channel)?;
+        Ok(queue
+            .declared_message_count()
+            .expect("queue must be declared non-immediate"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use amiquip::{AmqpProperties, Confirm, ConfirmPayload};
+
+    use crate::models::Todo;
+    use crate::queue::{
+        backoff_ms, last_error, on_failure, retry_count, retry_queue_name, todo_properties,
+        with_failure_headers, FailureAction, PublisherConfirms, RetryPolicy,
+    };
+    use crate::todo_domain::TodoDomain;
+
+    fn payload(delivery_tag: u64, multiple: bool) -> ConfirmPayload {
+        ConfirmPayload {
+            delivery_tag,
+            multiple,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_with_each_attempt() {
+        assert_eq!(backoff_ms(1, 1000), 1000);
+        assert_eq!(backoff_ms(2, 1000), 2000);
+        assert_eq!(backoff_ms(3, 1000), 4000);
+        assert_eq!(backoff_ms(100, 1000), 1000 << 20);
+    }
+
+    #[test]
+    fn each_retry_delay_has_its_own_queue() {
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            base_delay_ms: 500,
+        };
+        assert_eq!(policy.retry_delays(), vec![500, 1000, 2000]);
+        assert_eq!(retry_queue_name(2000), "work-retry-2000");
+
+        let capped = RetryPolicy {
+            max_attempts: 30,
+            base_delay_ms: 1,
+        };
+        assert_eq!(capped.retry_delays().len(), 21);
+    }
+
+    #[test]
+    fn it_dead_letters_once_attempts_are_exhausted() {
+        assert_eq!(on_failure(1, 3, 10), FailureAction::Retry { delay_ms: 10 });
+        assert_eq!(on_failure(2, 3, 10), FailureAction::Retry { delay_ms: 20 });
+        assert_eq!(on_failure(3, 3, 10), FailureAction::DeadLetter);
+    }
+
+    #[test]
+    fn failure_headers_round_trip() {
+        let properties = AmqpProperties::default().with_priority(3);
+        assert_eq!(retry_count(&properties), 0);
+        assert_eq!(last_error(&properties), None);
+
+        let failed = with_failure_headers(properties, 2, "missing row");
+        assert_eq!(retry_count(&failed), 2);
+        assert_eq!(last_error(&failed), Some("missing row".to_owned()));
+        assert_eq!(failed.priority(), &Some(3));
+    }
+
+    #[test]
+    fn the_message_id_is_the_todo_id() {
+        let todo = Todo {
+            id: 42,
+            domain: TodoDomain::Pairs,
+            other: 7,
+        };
+        assert_eq!(todo_properties(&todo).message_id(), &Some("42".to_owned()));
+    }
+
+    #[test]
+    fn it_waits_for_every_confirm_including_multiples() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let mut confirms = PublisherConfirms::new(receiver);
+        sender.send(Confirm::Ack(payload(2, false))).unwrap();
+        sender.send(Confirm::Ack(payload(3, true))).unwrap();
+        assert!(confirms.wait_for(3, Duration::from_millis(10)).is_ok());
+
+        sender.send(Confirm::Ack(payload(4, false))).unwrap();
+        assert!(confirms.wait_for(1, Duration::from_millis(10)).is_ok());
+    }
+
+    #[test]
+    fn a_nack_or_missing_confirm_is_an_error() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let mut confirms = PublisherConfirms::new(receiver);
+        sender.send(Confirm::Ack(payload(1, false))).unwrap();
+        sender.send(Confirm::Nack(payload(2, false))).unwrap();
+        assert!(confirms.wait_for(2, Duration::from_millis(10)).is_err());
+
+        assert!(confirms.wait_for(1, Duration::from_millis(10)).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use std::{env, time::Duration};
+
+use diesel::{ExpressionMethods, PgConnection, QueryDsl, RunQueryDsl};
+
+use crate::{
+    establish_connection_safe, models::Todo, pg_queue::TodoListener, processed_todos,
+    queue::WorkQueue, schema::todos, shutdown::Shutdown,
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct RelayConfig {
+    pub batch_size: i64,
+    pub poll_interval: Duration,
+    pub processed_retention: Duration,
+}
+
+impl RelayConfig {
+    pub fn from_env() -> RelayConfig {
+        RelayConfig {
+            batch_size: env::var("RELAY_BATCH_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(1000),
+            poll_interval: Duration::from_millis(
+                env::var("RELAY_POLL_INTERVAL_MS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(5000),
+            ),
+            processed_retention: processed_todos::retention_from_env(),
+        }
+    }
+}
+
+// Runs until a connection fails or shutdown is requested. A batch that is underway is finished
+// first. The caller decides whether to reconnect.
+pub fn relay_forever(
+    work_queue: &mut impl WorkQueue,
+    config: RelayConfig,
+    shutdown: &Shutdown,
+) -> Result<(), anyhow::Error> {
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let mut listener = TodoListener::connect(&database_url)?;
+    let conn = establish_connection_safe()?;
+
+    while !shutdown.requested() {
+        let amount = relay_batch(&conn, work_queue, config.batch_size)?;
+        if amount > 0 {
+            println!("successfully relayed {} messages", amount)
+        }
+        if (amount as i64) < config.batch_size {
+            let pruned = processed_todos::prune(&conn, config.processed_retention)?;
+            if pruned > 0 {
+                println!("pruned {} processed todos", pruned)
+            }
+            listener.wait(config.poll_interval, shutdown)?;
+        }
+    }
+    Ok(())
+}
+
+// Todos are only deleted once the queue has accepted every message in the batch. A crash after
+// publishing but before commit republishes the batch; workers skip the duplicates by todo id.
+pub fn relay_batch(
+    conn: &PgConnection,
+    work_queue: &mut impl WorkQueue,
+    batch_size: i64,
+) -> Result<usize, anyhow::Error> {
+    conn.build_transaction().serializable().run(|| {
+        let todos = get_todos(conn, batch_size)?;
+        work_queue.publish(&todos)?;
+        let number_published = todos.len();
+        delete_todos(conn, todos)?;
+        Ok(number_published)
+    })
+}
+
+fn get_todos(conn: &PgConnection, batch_size: i64) -> Result<Vec<Todo>, diesel::result::Error> {
+    todos::table.limit(batch_size).load(conn)
+}
+
+fn delete_todos(
+    conn: &PgConnection,
+    todos_to_delete: Vec<Todo>,
+) -> Result<usize, diesel::result::Error> {
+    let ids = todos_to_delete.iter().map(|t| t.id);
+    diesel::delete(todos::table.filter(todos::id.eq_any(ids))).execute(conn)
+}
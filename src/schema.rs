@@ -6,6 +6,16 @@ table! {
     }
 }
 
+table! {
+    hash_collisions (id) {
+        id -> Int4,
+        table_name -> Varchar,
+        hash -> Int8,
+        existing_id -> Int4,
+        attempted -> Text,
+    }
+}
+
 table! {
     orthotopes (id) {
         id -> Int4,
@@ -15,6 +25,8 @@ table! {
         contents -> Array<Int4>,
         base -> Bool,
         info_hash -> Int8,
+        dims -> Text,
+        volume -> Int4,
     }
 }
 
@@ -37,6 +49,13 @@ table! {
     }
 }
 
+table! {
+    processed_todos (todo_id) {
+        todo_id -> Int4,
+        claimed_at -> Timestamp,
+    }
+}
+
 table! {
     sentences (id) {
         id -> Int4,
@@ -45,6 +64,15 @@ table! {
     }
 }
 
+table! {
+    todo_claims (todo_id) {
+        todo_id -> Int4,
+        attempts -> Int4,
+        last_error -> Nullable<Text>,
+        available_at -> Timestamp,
+    }
+}
+
 table! {
     todos (id) {
         id -> Int4,
@@ -61,4 +89,4 @@ table! {
     }
 }
 
-allow_tables_to_appear_in_same_query!(books, orthotopes, pairs, phrases, sentences, todos, words,);
+allow_tables_to_appear_in_same_query!(books, hash_collisions, orthotopes, pairs, phrases, processed_todos, sentences, todo_claims, todos, words,);
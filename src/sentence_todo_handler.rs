@@ -1,23 +1,22 @@
 use std::collections::HashMap;
 
 use crate::models::{NewPair, NewPhrase, Pair, Phrase, Todo};
+use crate::todo_domain::TodoDomain;
 use crate::{
-    create_todo_entry, get_relevant_vocabulary, ints_to_big_int,
+    collisions, create_todo_entry, get_relevant_vocabulary, ints_to_big_int,
     vec_of_words_to_big_int, NewTodo, Word,
 };
+use diesel::dsl::any;
 use diesel::PgConnection;
 
-#[tracing::instrument(level = "info", skip(pool))]
-pub fn handle_sentence_todo(todo: Todo, pool: diesel::r2d2::Pool<diesel::r2d2::ConnectionManager<PgConnection>>) -> Result<(), anyhow::Error> {
-    let conn = pool.get()?;
-    conn.build_transaction().serializable().run(|| {
-        let sentence = get_sentence(&conn, todo.other)?;
-        let words = split_sentence(&sentence);
-        let vocab = get_relevant_vocabulary(&conn, words.into_iter().collect())?;
-        create_pairs(&conn, &sentence, &vocab)?;
-        create_phrases(&conn, sentence, &vocab)?;
-        Ok(())
-    })
+#[tracing::instrument(level = "info", skip(conn))]
+pub fn handle_sentence_todo(todo: &Todo, conn: &PgConnection) -> Result<(), anyhow::Error> {
+    let sentence = get_sentence(conn, todo.other)?;
+    let words = split_sentence(&sentence);
+    let vocab = get_relevant_vocabulary(conn, words.into_iter().collect())?;
+    create_pairs(conn, &sentence, &vocab)?;
+    create_phrases(conn, sentence, &vocab)?;
+    Ok(())
 }
 
 fn split_sentence(sentence: &str) -> Vec<String> {
@@ -46,11 +45,39 @@ fn create_pair_entry(
     to_insert: Vec<NewPair>,
 ) -> Result<Vec<Pair>, diesel::result::Error> {
     use crate::schema::pairs;
-    use diesel::RunQueryDsl;
-    diesel::insert_into(pairs::table)
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    let inserted: Vec<Pair> = diesel::insert_into(pairs::table)
         .values(&to_insert)
         .on_conflict_do_nothing()
-        .get_results(conn)
+        .get_results(conn)?;
+
+    let attempted: Vec<(i64, (Word, Word))> = to_insert
+        .into_iter()
+        .map(|p| (p.pair_hash, (p.first_word, p.second_word)))
+        .collect();
+    let mut existing: Vec<(i32, i64, (Word, Word))> = inserted
+        .iter()
+        .map(|p| (p.id, p.pair_hash, (p.first_word, p.second_word)))
+        .collect();
+    let missed = collisions::missed_hashes(&attempted, &existing);
+    if !missed.is_empty() {
+        let loaded: Vec<(i32, i64, Word, Word)> = pairs::table
+            .filter(pairs::pair_hash.eq(any(missed)))
+            .select((
+                pairs::id,
+                pairs::pair_hash,
+                pairs::first_word,
+                pairs::second_word,
+            ))
+            .load(conn)?;
+        existing.extend(loaded.into_iter().map(|(pk, h, f, s)| (pk, h, (f, s))));
+    }
+    collisions::record_collisions(
+        conn,
+        collisions::find_collisions("pairs", attempted, existing),
+    )?;
+
+    Ok(inserted)
 }
 
 fn create_phrases(
@@ -82,7 +109,7 @@ fn create_phrases(
     let to_insert: Vec<NewTodo> = phrases
         .iter()
         .map(|p| NewTodo {
-            domain: "phrases".to_owned(),
+            domain: TodoDomain::Phrases,
             other: p.id,
         })
         .collect();
@@ -96,14 +123,38 @@ fn create_phrase_entry(
     to_insert: Vec<NewPhrase>,
 ) -> Result<Vec<Phrase>, diesel::result::Error> {
     use crate::schema::phrases;
-    use diesel::RunQueryDsl;
-    diesel::insert_into(phrases::table)
+    use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+    let inserted: Vec<Phrase> = diesel::insert_into(phrases::table)
         .values(&to_insert)
         .on_conflict_do_nothing()
-        .get_results(conn)
+        .get_results(conn)?;
+
+    let attempted: Vec<(i64, Vec<Word>)> = to_insert
+        .into_iter()
+        .map(|p| (p.words_hash, p.words))
+        .collect();
+    let mut existing: Vec<(i32, i64, Vec<Word>)> = inserted
+        .iter()
+        .map(|p| (p.id, p.words_hash, p.words.clone()))
+        .collect();
+    let missed = collisions::missed_hashes(&attempted, &existing);
+    if !missed.is_empty() {
+        existing.extend(
+            phrases::table
+                .filter(phrases::words_hash.eq(any(missed)))
+                .select((phrases::id, phrases::words_hash, phrases::words))
+                .load::<(i32, i64, Vec<Word>)>(conn)?,
+        );
+    }
+    collisions::record_collisions(
+        conn,
+        collisions::find_collisions("phrases", attempted, existing),
+    )?;
+
+    Ok(inserted)
 }
 
-fn split_sentence_to_phrases(sentence: String) -> Vec<Vec<String>> {
+pub(crate) fn split_sentence_to_phrases(sentence: String) -> Vec<Vec<String>> {
     let words: Vec<String> = split_sentence(&sentence);
 
     heads(words)
@@ -157,7 +208,7 @@ fn create_pairs(
     let to_insert: Vec<NewTodo> = pairs
         .iter()
         .map(|p| NewTodo {
-            domain: "pairs".to_owned(),
+            domain: TodoDomain::Pairs,
             other: p.id,
         })
         .collect();
@@ -0,0 +1,67 @@
+use std::{
+    io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use signal_hook::consts::{SIGINT, SIGTERM};
+
+// How often blocking waits wake up to check for a shutdown request.
+pub const SHUTDOWN_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+// Exit status once a signal's work has been drained. Finishing the in-flight todos and stopping is
+// a success, which also keeps it apart from the 128 + signal of a forced exit.
+pub const CLEAN_EXIT_CODE: i32 = 0;
+
+// Exit status when a second signal cuts the drain short, the conventional 128 + signal number.
+pub fn forced_exit_code(signal: i32) -> i32 {
+    128 + signal
+}
+
+// Set once SIGTERM or SIGINT arrives. Workers and the relay check it between todos and batches, so
+// work already underway is committed and acked instead of being rolled back and redelivered. A
+// second signal exits immediately with `forced_exit_code`.
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+}
+
+impl Shutdown {
+    pub fn register() -> Result<Shutdown, io::Error> {
+        let shutdown = Shutdown::default();
+        for signal in [SIGTERM, SIGINT] {
+            // Registered first so that it only sees the flag set by an earlier signal.
+            signal_hook::flag::register_conditional_shutdown(
+                signal,
+                forced_exit_code(signal),
+                shutdown.requested.clone(),
+            )?;
+            signal_hook::flag::register(signal, shutdown.requested.clone())?;
+        }
+        Ok(shutdown)
+    }
+
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    pub fn requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shutdown::Shutdown;
+
+    #[test]
+    fn clones_share_the_request() {
+        let shutdown = Shutdown::default();
+        let worker = shutdown.clone();
+        shutdown.request();
+        assert!(worker.requested());
+    }
+}
@@ -0,0 +1,83 @@
+// Tables of the SQLite backend, from migrations_sqlite. The Postgres array columns are replaced by
+// phrase_words, ortho_hops and ortho_contents.
+
+table! {
+    ortho_contents (word, orthotope_id) {
+        word -> Integer,
+        orthotope_id -> Integer,
+    }
+}
+
+table! {
+    ortho_hops (word, orthotope_id) {
+        word -> Integer,
+        orthotope_id -> Integer,
+    }
+}
+
+table! {
+    orthotopes (id) {
+        id -> Integer,
+        information -> Binary,
+        origin -> Integer,
+        base -> Bool,
+        info_hash -> BigInt,
+    }
+}
+
+table! {
+    pairs (id) {
+        id -> Integer,
+        first_word -> Integer,
+        second_word -> Integer,
+        pair_hash -> BigInt,
+    }
+}
+
+table! {
+    phrase_words (phrase_id, position) {
+        phrase_id -> Integer,
+        position -> Integer,
+        word -> Integer,
+    }
+}
+
+table! {
+    phrases (id) {
+        id -> Integer,
+        phrase_head -> BigInt,
+        phrase_tail -> BigInt,
+        words_hash -> BigInt,
+    }
+}
+
+table! {
+    sentences (id) {
+        id -> Integer,
+        sentence -> Text,
+        sentence_hash -> BigInt,
+    }
+}
+
+table! {
+    words (id) {
+        id -> Integer,
+        word -> Text,
+        word_hash -> BigInt,
+    }
+}
+
+joinable!(ortho_contents -> orthotopes (orthotope_id));
+joinable!(ortho_hops -> orthotopes (orthotope_id));
+joinable!(phrase_words -> phrases (phrase_id));
+
+allow_tables_to_appear_in_same_query!(
+    ortho_contents,
+    ortho_hops,
+    orthotopes,
+    pairs,
+    phrase_words,
+    phrases,
+    sentences,
+    words,
+);
@@ -0,0 +1,482 @@
+use std::collections::HashSet;
+
+use diesel::{
+    connection::SimpleConnection, insert_or_ignore_into, migration::RunMigrationsError,
+    sqlite::SqliteConnection, BoolExpressionMethods, Connection, ConnectionError,
+    ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
+};
+
+use crate::{
+    fact_store::{FactStore, WritableFactStore},
+    ints_to_big_int,
+    ortho::Ortho,
+    ortho_encoding::decode_all,
+    ortho_to_orthotope,
+    sqlite_schema::{
+        ortho_contents, ortho_hops, orthotopes, pairs, phrase_words, phrases, sentences, words,
+    },
+    string_to_signed_int, vec_of_words_to_big_int, PairVocabulary, Word,
+};
+
+embed_migrations!("./migrations_sqlite");
+
+// Opens (creating if needed) a fold kept in a single SQLite file. `:memory:` gives a throwaway one.
+pub fn establish_sqlite_connection(path: &str) -> Result<SqliteConnection, ConnectionError> {
+    let conn = SqliteConnection::establish(path)?;
+    conn.batch_execute("PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL;")
+        .map_err(ConnectionError::CouldntSetupConfiguration)?;
+    Ok(conn)
+}
+
+pub fn run_sqlite_migrations(conn: &SqliteConnection) -> Result<(), RunMigrationsError> {
+    embedded_migrations::run(conn)
+}
+
+pub fn get_all_orthos(conn: &SqliteConnection) -> Result<Vec<Ortho>, anyhow::Error> {
+    let results: Vec<Vec<u8>> = orthotopes::table
+        .order(orthotopes::id)
+        .select(orthotopes::information)
+        .load(conn)?;
+    decode_all(&results)
+}
+
+fn orthos_with_hop_in(
+    conn: &SqliteConnection,
+    hop: Vec<Word>,
+    base_only: bool,
+) -> Result<Vec<Ortho>, anyhow::Error> {
+    let ids = ortho_hops::table
+        .filter(ortho_hops::word.eq_any(hop))
+        .select(ortho_hops::orthotope_id);
+    let results: Vec<Vec<u8>> = orthotopes::table
+        .filter(orthotopes::id.eq_any(ids))
+        .filter(orthotopes::base.eq(true).or(!base_only))
+        .select(orthotopes::information)
+        .load(conn)?;
+    decode_all(&results)
+}
+
+fn orthos_with_contents_in(
+    conn: &SqliteConnection,
+    contents: Vec<Word>,
+    base_only: bool,
+) -> Result<Vec<Ortho>, anyhow::Error> {
+    let ids = ortho_contents::table
+        .filter(ortho_contents::word.eq_any(contents))
+        .select(ortho_contents::orthotope_id);
+    let results: Vec<Vec<u8>> = orthotopes::table
+        .filter(orthotopes::id.eq_any(ids))
+        .filter(orthotopes::base.eq(true).or(!base_only))
+        .select(orthotopes::information)
+        .load(conn)?;
+    decode_all(&results)
+}
+
+fn pairs_with_words_in(
+    conn: &SqliteConnection,
+    first_words: HashSet<Word>,
+    second_words: HashSet<Word>,
+) -> Result<Vec<(Word, Word, i64)>, anyhow::Error> {
+    let results = pairs::table
+        .filter(pairs::first_word.eq_any(Vec::from_iter(first_words)))
+        .filter(pairs::second_word.eq_any(Vec::from_iter(second_words)))
+        .select((pairs::first_word, pairs::second_word, pairs::pair_hash))
+        .load(conn)?;
+    Ok(results)
+}
+
+impl FactStore for SqliteConnection {
+    fn get_ortho_by_origin(&self, origin: Word) -> Result<Vec<Ortho>, anyhow::Error> {
+        let results: Vec<Vec<u8>> = orthotopes::table
+            .filter(orthotopes::origin.eq(origin))
+            .select(orthotopes::information)
+            .load(self)?;
+        decode_all(&results)
+    }
+
+    fn get_base_ortho_by_origin(&self, origin: Word) -> Result<Vec<Ortho>, anyhow::Error> {
+        let results: Vec<Vec<u8>> = orthotopes::table
+            .filter(orthotopes::origin.eq(origin))
+            .filter(orthotopes::base.eq(true))
+            .select(orthotopes::information)
+            .load(self)?;
+        decode_all(&results)
+    }
+
+    fn get_ortho_by_origin_batch(
+        &self,
+        origins: HashSet<Word>,
+    ) -> Result<Vec<Ortho>, anyhow::Error> {
+        let results: Vec<Vec<u8>> = orthotopes::table
+            .filter(orthotopes::origin.eq_any(Vec::from_iter(origins)))
+            .select(orthotopes::information)
+            .load(self)?;
+        decode_all(&results)
+    }
+
+    fn get_ortho_by_hop(&self, hop: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error> {
+        orthos_with_hop_in(self, hop, false)
+    }
+
+    fn get_base_ortho_by_hop(&self, hop: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error> {
+        orthos_with_hop_in(self, hop, true)
+    }
+
+    fn get_ortho_by_contents(&self, contents: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error> {
+        orthos_with_contents_in(self, contents, false)
+    }
+
+    fn get_base_ortho_by_contents(&self, contents: Vec<Word>) -> Result<Vec<Ortho>, anyhow::Error> {
+        orthos_with_contents_in(self, contents, true)
+    }
+
+    fn single_ffbb(&self, first: Word, second: Word) -> Result<Vec<Ortho>, anyhow::Error> {
+        let cs = self.project_forward(first)?;
+        let ds = self.project_forward(second)?;
+        let found = pairs_with_words_in(self, cs, ds)?;
+        Ok(found
+            .into_iter()
+            .filter(|(c, _, _)| *c != second)
+            .map(|(c, d, _)| Ortho::new(first, second, c, d))
+            .collect())
+    }
+
+    fn single_fbbf(&self, first: Word, second: Word) -> Result<Vec<Ortho>, anyhow::Error> {
+        let as_ = self.project_backward(first)?;
+        let cs = self.project_backward(second)?;
+        let found = pairs_with_words_in(self, as_, cs)?;
+        Ok(found
+            .into_iter()
+            .filter(|(_, c, _)| *c != first)
+            .map(|(a, c, _)| Ortho::new(a, first, c, second))
+            .collect())
+    }
+
+    fn project_forward(&self, from: Word) -> Result<HashSet<Word>, anyhow::Error> {
+        let seconds: Vec<Word> = pairs::table
+            .filter(pairs::first_word.eq(from))
+            .select(pairs::second_word)
+            .load(self)?;
+        Ok(HashSet::from_iter(seconds))
+    }
+
+    fn project_backward(&self, from: Word) -> Result<HashSet<Word>, anyhow::Error> {
+        let firsts: Vec<Word> = pairs::table
+            .filter(pairs::second_word.eq(from))
+            .select(pairs::first_word)
+            .load(self)?;
+        Ok(HashSet::from_iter(firsts))
+    }
+
+    fn project_forward_batch(
+        &self,
+        from: HashSet<Word>,
+    ) -> Result<HashSet<(Word, Word)>, anyhow::Error> {
+        let found: Vec<(Word, Word)> = pairs::table
+            .filter(pairs::first_word.eq_any(Vec::from_iter(from)))
+            .select((pairs::first_word, pairs::second_word))
+            .load(self)?;
+        Ok(HashSet::from_iter(found))
+    }
+
+    fn project_backward_batch(
+        &self,
+        from: HashSet<Word>,
+    ) -> Result<HashSet<(Word, Word)>, anyhow::Error> {
+        let found: Vec<(Word, Word)> = pairs::table
+            .filter(pairs::second_word.eq_any(Vec::from_iter(from)))
+            .select((pairs::first_word, pairs::second_word))
+            .load(self)?;
+        Ok(HashSet::from_iter(found))
+    }
+
+    fn get_hashes_of_pairs_with_words_in(
+        &self,
+        first_words: HashSet<Word>,
+        second_words: HashSet<Word>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        Ok(pairs_with_words_in(self, first_words, second_words)?
+            .into_iter()
+            .map(|(_, _, h)| h)
+            .collect())
+    }
+
+    fn get_hashes_and_words_of_pairs_with_words_in(
+        &self,
+        first_words: HashSet<Word>,
+        second_words: HashSet<Word>,
+    ) -> Result<PairVocabulary, anyhow::Error> {
+        let mut firsts = HashSet::new();
+        let mut seconds = HashSet::new();
+        let mut hashes = HashSet::new();
+        for (f, s, h) in pairs_with_words_in(self, first_words, second_words)? {
+            firsts.insert(f);
+            seconds.insert(s);
+            hashes.insert(h);
+        }
+        Ok((firsts, seconds, hashes))
+    }
+
+    fn get_phrases_with_matching_hashes(
+        &self,
+        hashes: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        let found: Vec<i64> = phrases::table
+            .filter(phrases::words_hash.eq_any(Vec::from_iter(hashes)))
+            .select(phrases::words_hash)
+            .load(self)?;
+        Ok(HashSet::from_iter(found))
+    }
+
+    fn phrase_exists_db_filter(
+        &self,
+        heads: HashSet<i64>,
+        tails: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        let found: Vec<i64> = phrases::table
+            .filter(phrases::phrase_head.eq_any(Vec::from_iter(heads)))
+            .filter(phrases::phrase_tail.eq_any(Vec::from_iter(tails)))
+            .select(phrases::words_hash)
+            .load(self)?;
+        Ok(HashSet::from_iter(found))
+    }
+
+    fn phrase_exists_db_filter_head(
+        &self,
+        heads: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        let found: Vec<i64> = phrases::table
+            .filter(phrases::phrase_head.eq_any(Vec::from_iter(heads)))
+            .select(phrases::words_hash)
+            .load(self)?;
+        Ok(HashSet::from_iter(found))
+    }
+
+    fn phrase_exists_db_filter_tail(
+        &self,
+        tails: HashSet<i64>,
+    ) -> Result<HashSet<i64>, anyhow::Error> {
+        let found: Vec<i64> = phrases::table
+            .filter(phrases::phrase_tail.eq_any(Vec::from_iter(tails)))
+            .select(phrases::words_hash)
+            .load(self)?;
+        Ok(HashSet::from_iter(found))
+    }
+}
+
+// Rows are keyed by the same hashes as in Postgres and skipped when the hash is already there.
+// SQLite cannot return the inserted row, so ids are looked up by hash afterwards.
+impl WritableFactStore for SqliteConnection {
+    fn add_word(&mut self, word: &str) -> Result<Word, anyhow::Error> {
+        let word_hash = string_to_signed_int(word);
+        let existing = words::table
+            .filter(words::word_hash.eq(word_hash))
+            .select(words::id)
+            .first(self)
+            .optional()?;
+        if let Some(id) = existing {
+            return Ok(id);
+        }
+        diesel::insert_into(words::table)
+            .values((words::word.eq(word), words::word_hash.eq(word_hash)))
+            .execute(self)?;
+        Ok(words::table
+            .filter(words::word_hash.eq(word_hash))
+            .select(words::id)
+            .first(self)?)
+    }
+
+    fn add_sentence(&mut self, sentence: &str) -> Result<bool, anyhow::Error> {
+        let inserted = insert_or_ignore_into(sentences::table)
+            .values((
+                sentences::sentence.eq(sentence),
+                sentences::sentence_hash.eq(string_to_signed_int(sentence)),
+            ))
+            .execute(self)?;
+        Ok(inserted == 1)
+    }
+
+    fn add_pair(&mut self, first: Word, second: Word) -> Result<bool, anyhow::Error> {
+        let inserted = insert_or_ignore_into(pairs::table)
+            .values((
+                pairs::first_word.eq(first),
+                pairs::second_word.eq(second),
+                pairs::pair_hash.eq(ints_to_big_int(first, second)),
+            ))
+            .execute(self)?;
+        Ok(inserted == 1)
+    }
+
+    fn add_phrase(&mut self, words: Vec<Word>) -> Result<bool, anyhow::Error> {
+        let conn: &SqliteConnection = self;
+        conn.transaction(|| {
+            let words_hash = vec_of_words_to_big_int(words.clone());
+            let inserted = insert_or_ignore_into(phrases::table)
+                .values((
+                    phrases::phrase_head
+                        .eq(vec_of_words_to_big_int(words[..words.len() - 1].to_vec())),
+                    phrases::phrase_tail.eq(vec_of_words_to_big_int(words[1..].to_vec())),
+                    phrases::words_hash.eq(words_hash),
+                ))
+                .execute(conn)?;
+            if inserted == 0 {
+                return Ok(false);
+            }
+            let phrase_id: i32 = phrases::table
+                .filter(phrases::words_hash.eq(words_hash))
+                .select(phrases::id)
+                .first(conn)?;
+            let rows: Vec<_> = words
+                .iter()
+                .enumerate()
+                .map(|(position, word)| {
+                    (
+                        phrase_words::phrase_id.eq(phrase_id),
+                        phrase_words::position.eq(position as i32),
+                        phrase_words::word.eq(*word),
+                    )
+                })
+                .collect();
+            diesel::insert_into(phrase_words::table)
+                .values(&rows)
+                .execute(conn)?;
+            Ok(true)
+        })
+    }
+
+    fn add_ortho(&mut self, ortho: Ortho) -> Result<bool, anyhow::Error> {
+        let conn: &SqliteConnection = self;
+        let orthotope = ortho_to_orthotope(&ortho);
+        conn.transaction(|| {
+            let inserted = insert_or_ignore_into(orthotopes::table)
+                .values((
+                    orthotopes::information.eq(&orthotope.information),
+                    orthotopes::origin.eq(orthotope.origin),
+                    orthotopes::base.eq(orthotope.base),
+                    orthotopes::info_hash.eq(orthotope.info_hash),
+                ))
+                .execute(conn)?;
+            if inserted == 0 {
+                return Ok(false);
+            }
+            let orthotope_id: i32 = orthotopes::table
+                .filter(orthotopes::info_hash.eq(orthotope.info_hash))
+                .select(orthotopes::id)
+                .first(conn)?;
+            let hops: Vec<_> = orthotope
+                .hop
+                .iter()
+                .map(|w| {
+                    (
+                        ortho_hops::word.eq(*w),
+                        ortho_hops::orthotope_id.eq(orthotope_id),
+                    )
+                })
+                .collect();
+            diesel::insert_into(ortho_hops::table)
+                .values(&hops)
+                .execute(conn)?;
+            let contents: Vec<_> = orthotope
+                .contents
+                .iter()
+                .map(|w| {
+                    (
+                        ortho_contents::word.eq(*w),
+                        ortho_contents::orthotope_id.eq(orthotope_id),
+                    )
+                })
+                .collect();
+            diesel::insert_into(ortho_contents::table)
+                .values(&contents)
+                .execute(conn)?;
+            Ok(true)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use diesel::SqliteConnection;
+    use maplit::hashset;
+
+    use crate::{
+        fact_store::{FactStore, InMemoryFactStore, WritableFactStore},
+        fold::Folder,
+        ortho::Ortho,
+        sqlite_store::{establish_sqlite_connection, get_all_orthos, run_sqlite_migrations},
+        vec_of_words_to_big_int,
+    };
+
+    fn connection() -> SqliteConnection {
+        let conn = establish_sqlite_connection(":memory:").unwrap();
+        run_sqlite_migrations(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn it_looks_orthos_up_through_the_lookup_tables() {
+        let abcd = Ortho::new(1, 2, 3, 4);
+        let bedf = Ortho::new(2, 5, 4, 6);
+        let mut conn = connection();
+        assert!(conn.add_ortho(abcd.clone()).unwrap());
+        assert!(conn.add_ortho(bedf.clone()).unwrap());
+        assert!(!conn.add_ortho(abcd.clone()).unwrap());
+
+        assert_eq!(conn.get_ortho_by_origin(1).unwrap(), vec![abcd.clone()]);
+        assert_eq!(
+            conn.get_ortho_by_hop(vec![4, 3]).unwrap(),
+            vec![abcd.clone(), bedf.clone()]
+        );
+        assert_eq!(conn.get_ortho_by_contents(vec![6]).unwrap(), vec![bedf]);
+        assert_eq!(conn.get_base_ortho_by_hop(vec![2]).unwrap(), vec![abcd]);
+    }
+
+    #[test]
+    fn it_finds_pairs_and_phrases() {
+        let mut conn = connection();
+        for (f, s) in [(1, 2), (3, 4), (1, 3), (2, 4)] {
+            assert!(conn.add_pair(f, s).unwrap());
+        }
+        assert!(!conn.add_pair(1, 2).unwrap());
+        assert!(conn.add_phrase(vec![1, 2, 5]).unwrap());
+        assert!(!conn.add_phrase(vec![1, 2, 5]).unwrap());
+
+        assert_eq!(
+            conn.single_ffbb(1, 2).unwrap(),
+            vec![Ortho::new(1, 2, 3, 4)]
+        );
+        assert_eq!(
+            conn.single_fbbf(2, 4).unwrap(),
+            vec![Ortho::new(1, 2, 3, 4)]
+        );
+        assert_eq!(conn.project_backward(4).unwrap(), hashset! {2, 3});
+        assert_eq!(
+            conn.phrase_exists_db_filter(
+                hashset! {vec_of_words_to_big_int(vec![1, 2])},
+                hashset! {vec_of_words_to_big_int(vec![2, 5])},
+            )
+            .unwrap(),
+            hashset! {vec_of_words_to_big_int(vec![1, 2, 5])}
+        );
+    }
+
+    #[test]
+    fn it_folds_the_same_orthos_as_the_in_memory_store() {
+        let books = [
+            "a b c d. a c. b d. a b.",
+            "b e. d f. b d. e f.",
+            "c d f. a b e.",
+        ];
+        let mut in_memory = Folder::with_store(InMemoryFactStore::new());
+        let mut sqlite = Folder::with_store(connection());
+        for book in books {
+            in_memory.add_text(book).unwrap();
+            sqlite.add_text(book).unwrap();
+        }
+
+        assert_eq!(
+            get_all_orthos(sqlite.store()).unwrap(),
+            in_memory.orthos().to_vec()
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use siphasher::sip::SipHasher13;
+use std::hash::Hasher;
+
+// Every hash that lands in a unique column goes through here. The algorithm (SipHash-1-3 with
+// zero keys) and the byte layout fed into it are fixed, so the values do not move when the
+// toolchain changes. Changing anything in this file requires running `maintenance rehash`.
+pub struct StableHasher {
+    inner: SipHasher13,
+}
+
+impl StableHasher {
+    pub fn new() -> StableHasher {
+        StableHasher {
+            inner: SipHasher13::new_with_keys(0, 0),
+        }
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_len(bytes.len());
+        self.inner.write(bytes);
+    }
+
+    pub fn write_len(&mut self, len: usize) {
+        self.inner.write(&(len as u64).to_le_bytes());
+    }
+
+    pub fn write_i32(&mut self, i: i32) {
+        self.inner.write(&i.to_le_bytes());
+    }
+
+    pub fn write_i64(&mut self, i: i64) {
+        self.inner.write(&i.to_le_bytes());
+    }
+
+    pub fn finish(&self) -> i64 {
+        self.inner.finish() as i64
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pair_todo_handler::data_vec_to_signed_int;
+    use crate::{
+        ints_to_big_int, string_refs_to_signed_int, string_to_signed_int,
+        vec_of_big_ints_to_big_int, vec_of_words_to_big_int,
+    };
+
+    // These values are persisted in the database. If this test fails, existing data is invalid.
+    #[test]
+    fn hashes_are_pinned() {
+        assert_eq!(string_to_signed_int("king"), -594900612812337082);
+        assert_eq!(
+            string_refs_to_signed_int("the", "king"),
+            5954172022167715305
+        );
+        assert_eq!(vec_of_words_to_big_int(vec![1, 2, 3]), -4861658496802288406);
+        assert_eq!(
+            vec_of_big_ints_to_big_int(vec![1, 2, 3]),
+            1561758979349565031
+        );
+        assert_eq!(ints_to_big_int(1, 2), -7741499035202279953);
+        assert_eq!(data_vec_to_signed_int(&[1, 2, 3]), 8086395815454877121);
+    }
+
+    #[test]
+    fn string_boundaries_matter() {
+        assert_ne!(
+            string_refs_to_signed_int("ab", "c"),
+            string_refs_to_signed_int("a", "bc")
+        );
+    }
+
+    #[test]
+    fn order_matters() {
+        assert_ne!(ints_to_big_int(1, 2), ints_to_big_int(2, 1));
+        assert_ne!(
+            vec_of_words_to_big_int(vec![1, 2]),
+            vec_of_words_to_big_int(vec![2, 1])
+        );
+    }
+}
@@ -0,0 +1,254 @@
+use std::{fmt, io::Write, str::FromStr};
+
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Varchar,
+};
+use serde::{Deserialize, Serialize};
+
+// Serialized as its string name so that messages already sitting in the queue keep decoding.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, AsExpression, FromSqlRow,
+)]
+#[serde(into = "String", try_from = "String")]
+#[sql_type = "Varchar"]
+pub enum TodoDomain {
+    Books,
+    Sentences,
+    Pairs,
+    PairUp,
+    ExNihiloFfbb,
+    ExNihiloFbbf,
+    UpByOrigin,
+    UpByHop,
+    UpByContents,
+    Phrases,
+    PhraseByOrigin,
+    PhraseByHop,
+    PhraseByContents,
+    Orthotopes,
+    OrthoUp,
+    OrthoUpForward,
+    OrthoUpBack,
+    OrthoOver,
+    OrthoOverForward,
+    OrthoOverBack,
+}
+
+impl TodoDomain {
+    pub const ALL: [TodoDomain; 20] = [
+        TodoDomain::Books,
+        TodoDomain::Sentences,
+        TodoDomain::Pairs,
+        TodoDomain::PairUp,
+        TodoDomain::ExNihiloFfbb,
+        TodoDomain::ExNihiloFbbf,
+        TodoDomain::UpByOrigin,
+        TodoDomain::UpByHop,
+        TodoDomain::UpByContents,
+        TodoDomain::Phrases,
+        TodoDomain::PhraseByOrigin,
+        TodoDomain::PhraseByHop,
+        TodoDomain::PhraseByContents,
+        TodoDomain::Orthotopes,
+        TodoDomain::OrthoUp,
+        TodoDomain::OrthoUpForward,
+        TodoDomain::OrthoUpBack,
+        TodoDomain::OrthoOver,
+        TodoDomain::OrthoOverForward,
+        TodoDomain::OrthoOverBack,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoDomain::Books => "books",
+            TodoDomain::Sentences => "sentences",
+            TodoDomain::Pairs => "pairs",
+            TodoDomain::PairUp => "pair_up",
+            TodoDomain::ExNihiloFfbb => "ex_nihilo_ffbb",
+            TodoDomain::ExNihiloFbbf => "ex_nihilo_fbbf",
+            TodoDomain::UpByOrigin => "up_by_origin",
+            TodoDomain::UpByHop => "up_by_hop",
+            TodoDomain::UpByContents => "up_by_contents",
+            TodoDomain::Phrases => "phrases",
+            TodoDomain::PhraseByOrigin => "phrase_by_origin",
+            TodoDomain::PhraseByHop => "phrase_by_hop",
+            TodoDomain::PhraseByContents => "phrase_by_contents",
+            TodoDomain::Orthotopes => "orthotopes",
+            TodoDomain::OrthoUp => "ortho_up",
+            TodoDomain::OrthoUpForward => "ortho_up_forward",
+            TodoDomain::OrthoUpBack => "ortho_up_back",
+            TodoDomain::OrthoOver => "ortho_over",
+            TodoDomain::OrthoOverForward => "ortho_over_forward",
+            TodoDomain::OrthoOverBack => "ortho_over_back",
+        }
+    }
+
+    // Earlier steps in the derivation get lower priorities so the queue drains depth first.
+    pub fn priority(&self) -> u8 {
+        match self {
+            TodoDomain::Books => 1,
+            TodoDomain::Sentences => 2,
+            TodoDomain::Pairs => 3,
+            TodoDomain::PairUp => 4,
+            TodoDomain::ExNihiloFfbb => 5,
+            TodoDomain::ExNihiloFbbf => 6,
+            TodoDomain::UpByOrigin => 7,
+            TodoDomain::UpByHop => 8,
+            TodoDomain::UpByContents => 9,
+            TodoDomain::Phrases => 10,
+            TodoDomain::PhraseByOrigin => 11,
+            TodoDomain::PhraseByHop => 12,
+            TodoDomain::PhraseByContents => 13,
+            TodoDomain::Orthotopes => 14,
+            TodoDomain::OrthoUp => 15,
+            TodoDomain::OrthoUpForward => 16,
+            TodoDomain::OrthoUpBack => 17,
+            TodoDomain::OrthoOver => 18,
+            TodoDomain::OrthoOverForward => 19,
+            TodoDomain::OrthoOverBack => 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownTodoDomain(pub String);
+
+impl fmt::Display for UnknownTodoDomain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown todo domain: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownTodoDomain {}
+
+impl FromStr for TodoDomain {
+    type Err = UnknownTodoDomain;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TodoDomain::ALL
+            .into_iter()
+            .find(|d| d.as_str() == s)
+            .ok_or_else(|| UnknownTodoDomain(s.to_owned()))
+    }
+}
+
+impl TryFrom<String> for TodoDomain {
+    type Error = UnknownTodoDomain;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<TodoDomain> for String {
+    fn from(d: TodoDomain) -> Self {
+        d.as_str().to_owned()
+    }
+}
+
+impl fmt::Display for TodoDomain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ToSql<Varchar, Pg> for TodoDomain {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        ToSql::<Varchar, Pg>::to_sql(self.as_str(), out)
+    }
+}
+
+impl FromSql<Varchar, Pg> for TodoDomain {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let s: String = FromSql::<Varchar, Pg>::from_sql(bytes)?;
+        Ok(s.parse()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::todo_domain::{TodoDomain, UnknownTodoDomain};
+
+    #[test]
+    fn it_round_trips_through_its_name() {
+        for domain in TodoDomain::ALL {
+            assert_eq!(domain.as_str().parse(), Ok(domain));
+        }
+    }
+
+    // Each variant after the one before, with no wildcard arm, so a new variant does not compile
+    // until it is chained in here.
+    fn next(domain: TodoDomain) -> Option<TodoDomain> {
+        match domain {
+            TodoDomain::Books => Some(TodoDomain::Sentences),
+            TodoDomain::Sentences => Some(TodoDomain::Pairs),
+            TodoDomain::Pairs => Some(TodoDomain::PairUp),
+            TodoDomain::PairUp => Some(TodoDomain::ExNihiloFfbb),
+            TodoDomain::ExNihiloFfbb => Some(TodoDomain::ExNihiloFbbf),
+            TodoDomain::ExNihiloFbbf => Some(TodoDomain::UpByOrigin),
+            TodoDomain::UpByOrigin => Some(TodoDomain::UpByHop),
+            TodoDomain::UpByHop => Some(TodoDomain::UpByContents),
+            TodoDomain::UpByContents => Some(TodoDomain::Phrases),
+            TodoDomain::Phrases => Some(TodoDomain::PhraseByOrigin),
+            TodoDomain::PhraseByOrigin => Some(TodoDomain::PhraseByHop),
+            TodoDomain::PhraseByHop => Some(TodoDomain::PhraseByContents),
+            TodoDomain::PhraseByContents => Some(TodoDomain::Orthotopes),
+            TodoDomain::Orthotopes => Some(TodoDomain::OrthoUp),
+            TodoDomain::OrthoUp => Some(TodoDomain::OrthoUpForward),
+            TodoDomain::OrthoUpForward => Some(TodoDomain::OrthoUpBack),
+            TodoDomain::OrthoUpBack => Some(TodoDomain::OrthoOver),
+            TodoDomain::OrthoOver => Some(TodoDomain::OrthoOverForward),
+            TodoDomain::OrthoOverForward => Some(TodoDomain::OrthoOverBack),
+            TodoDomain::OrthoOverBack => None,
+        }
+    }
+
+    #[test]
+    fn every_variant_is_listed_and_round_trips() {
+        let variants: Vec<TodoDomain> =
+            std::iter::successors(Some(TodoDomain::Books), |d| next(*d)).collect();
+
+        assert_eq!(variants, TodoDomain::ALL.to_vec());
+        for domain in variants {
+            assert_eq!(TodoDomain::try_from(domain.as_str().to_owned()), Ok(domain));
+        }
+    }
+
+    #[test]
+    fn it_rejects_unknown_names() {
+        assert_eq!(
+            "nonsense".parse::<TodoDomain>(),
+            Err(UnknownTodoDomain("nonsense".to_owned()))
+        );
+    }
+
+    #[test]
+    fn every_domain_has_a_distinct_priority() {
+        let priorities: HashSet<u8> = TodoDomain::ALL.iter().map(|d| d.priority()).collect();
+        assert_eq!(priorities.len(), TodoDomain::ALL.len());
+    }
+
+    #[test]
+    fn it_decodes_messages_serialized_as_plain_strings() {
+        let old_message = bincode::serialize("ortho_up_forward").unwrap();
+        let decoded: TodoDomain = bincode::deserialize(&old_message).unwrap();
+        assert_eq!(decoded, TodoDomain::OrthoUpForward);
+
+        let bad_message = bincode::serialize("nonsense").unwrap();
+        assert!(bincode::deserialize::<TodoDomain>(&bad_message).is_err());
+    }
+
+    #[test]
+    fn the_database_constraint_lists_every_domain() {
+        let migration =
+            include_str!("../migrations/2026-10-18-130000_constrain_todo_domain/up.sql");
+        for domain in TodoDomain::ALL {
+            assert!(migration.contains(&format!("'{}'", domain.as_str())));
+        }
+    }
+}
@@ -4,33 +4,23 @@ use std::collections::HashSet;
 
 use crate::ortho::Ortho;
 
-use crate::{ints_to_big_int, up_helper, FailableWordToOrthoVec, FailableWordVecToOrthoVec, Word, FailableWordsetsToTupleWordsets};
-use diesel::PgConnection;
-
-#[tracing::instrument(
-    level = "info",
-    skip(
-        conn,
-        get_base_ortho_by_origin,
-        get_hashes_and_words_of_pairs_with_words_in
-    )
-)]
+use crate::fact_store::FactStore;
+use crate::{ints_to_big_int, up_helper, Word};
+
+#[tracing::instrument(level = "info", skip(store))]
 pub fn up_by_origin(
-    conn: Option<&PgConnection>,
+    store: &impl FactStore,
     first_w: Word,
     second_w: Word,
-    get_base_ortho_by_origin: FailableWordToOrthoVec,
-    get_hashes_and_words_of_pairs_with_words_in: FailableWordsetsToTupleWordsets,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
-    let left_orthos_by_origin: Vec<Ortho> = get_base_ortho_by_origin(conn, first_w)?;
-    let right_orthos_by_origin: Vec<Ortho> = get_base_ortho_by_origin(conn, second_w)?;
+    let left_orthos_by_origin: Vec<Ortho> = store.get_base_ortho_by_origin(first_w)?;
+    let right_orthos_by_origin: Vec<Ortho> = store.get_base_ortho_by_origin(second_w)?;
 
     if left_orthos_by_origin.is_empty() || right_orthos_by_origin.is_empty() {
-        return Ok(vec![])
+        return Ok(vec![]);
     }
 
-    let (all_firsts, all_seconds, all_pairs) = get_hashes_and_words_of_pairs_with_words_in(
-        conn,
+    let (all_firsts, all_seconds, all_pairs) = store.get_hashes_and_words_of_pairs_with_words_in(
         total_vocabulary(&left_orthos_by_origin),
         total_vocabulary(&right_orthos_by_origin),
     )?;
@@ -45,61 +35,41 @@ pub fn up_by_origin(
     ))
 }
 
-#[tracing::instrument(
-    level = "info",
-    skip(
-        conn,
-        get_base_ortho_by_hop,
-        get_hashes_and_words_of_pairs_with_words_in
-    )
-)]
+#[tracing::instrument(level = "info", skip(store))]
 pub fn up_by_hop(
-    conn: Option<&PgConnection>,
+    store: &impl FactStore,
     first_w: Word,
     second_w: Word,
-    get_base_ortho_by_hop: FailableWordVecToOrthoVec,
-    get_hashes_and_words_of_pairs_with_words_in: FailableWordsetsToTupleWordsets,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
-    let hop_left_orthos: Vec<Ortho> = get_base_ortho_by_hop(conn, vec![first_w])?;
-    let hop_right_orthos: Vec<Ortho> = get_base_ortho_by_hop(conn, vec![second_w])?;
+    let hop_left_orthos: Vec<Ortho> = store.get_base_ortho_by_hop(vec![first_w])?;
+    let hop_right_orthos: Vec<Ortho> = store.get_base_ortho_by_hop(vec![second_w])?;
 
     if hop_left_orthos.is_empty() || hop_right_orthos.is_empty() {
-        return Ok(vec![])
+        return Ok(vec![]);
     }
 
     find_corresponding_non_origin_checked_orthos_and_attempt_up(
-        get_hashes_and_words_of_pairs_with_words_in,
-        conn,
+        store,
         hop_left_orthos,
         hop_right_orthos,
     )
 }
 
-#[tracing::instrument(
-    level = "info",
-    skip(
-        conn,
-        get_base_ortho_by_contents,
-        get_hashes_and_words_of_pairs_with_words_in
-    )
-)]
+#[tracing::instrument(level = "info", skip(store))]
 pub fn up_by_contents(
-    conn: Option<&PgConnection>,
+    store: &impl FactStore,
     first_w: Word,
     second_w: Word,
-    get_base_ortho_by_contents: FailableWordVecToOrthoVec,
-    get_hashes_and_words_of_pairs_with_words_in: FailableWordsetsToTupleWordsets,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
-    let contents_left_orthos: Vec<Ortho> = get_base_ortho_by_contents(conn, vec![first_w])?;
-    let contents_right_orthos: Vec<Ortho> = get_base_ortho_by_contents(conn, vec![second_w])?;
+    let contents_left_orthos: Vec<Ortho> = store.get_base_ortho_by_contents(vec![first_w])?;
+    let contents_right_orthos: Vec<Ortho> = store.get_base_ortho_by_contents(vec![second_w])?;
 
     if contents_left_orthos.is_empty() || contents_right_orthos.is_empty() {
-        return Ok(vec![])
+        return Ok(vec![]);
     }
 
     find_corresponding_non_origin_checked_orthos_and_attempt_up(
-        get_hashes_and_words_of_pairs_with_words_in,
-        conn,
+        store,
         contents_left_orthos,
         contents_right_orthos,
     )
@@ -127,18 +97,13 @@ fn attempt_up_for_pairs_of_matching_dimensionality(
     .collect()
 }
 
-#[tracing::instrument(
-    level = "info",
-    skip(conn, get_hashes_and_words_of_pairs_with_words_in)
-)]
+#[tracing::instrument(level = "info", skip(store))]
 fn find_corresponding_non_origin_checked_orthos_and_attempt_up(
-    get_hashes_and_words_of_pairs_with_words_in: FailableWordsetsToTupleWordsets,
-    conn: Option<&PgConnection>,
+    store: &impl FactStore,
     hop_left_orthos: Vec<Ortho>,
     hop_right_orthos: Vec<Ortho>,
 ) -> Result<Vec<Ortho>, Error> {
-    let (all_firsts, all_seconds, all_pairs) = get_hashes_and_words_of_pairs_with_words_in(
-        conn,
+    let (all_firsts, all_seconds, all_pairs) = store.get_hashes_and_words_of_pairs_with_words_in(
         total_vocabulary(&hop_left_orthos),
         total_vocabulary(&hop_right_orthos),
     )?;
@@ -201,121 +166,16 @@ fn total_vocabulary(orthos: &[Ortho]) -> HashSet<i32> {
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
-
+    use crate::fact_store::InMemoryFactStore;
     use crate::ortho::Ortho;
     use crate::up_handler::{up_by_contents, up_by_hop, up_by_origin};
-    use crate::{ints_to_big_int, Word};
-    use diesel::PgConnection;
-    use maplit::{btreemap, hashset};
-
-    fn fake_ortho_by_origin_two(
-        _conn: Option<&PgConnection>,
-        o: Word,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
-        let mut pairs = btreemap! { 1 => vec![Ortho::new(
-            1,
-            2,
-            3,
-            3,
-        )], 5 => vec![Ortho::new(
-            5,
-            6,
-            3,
-            8,
-        )]};
-        Ok(pairs.entry(o).or_default().to_owned())
-    }
-
-    fn fake_ortho_by_origin_four(
-        _conn: Option<&PgConnection>,
-        o: Word,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
-        let single = Ortho::new(1, 2, 3, 4);
-        let l_one = Ortho::new(5, 6, 7, 8);
-
-        let r_one = Ortho::new(9, 10, 11, 12);
-
-        let combined = Ortho::zip_up(&l_one, &r_one, &btreemap! { 10 => 6, 11 => 7 });
+    use crate::Word;
+    use maplit::btreemap;
 
-        let mut pairs = btreemap! { 1 => vec![single], 5 => vec![combined]};
-
-        Ok(pairs.entry(o).or_default().to_owned())
-    }
-
-    fn fake_ortho_by_origin_three(
-        _conn: Option<&PgConnection>,
-        o: Word,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
-        let l_one = Ortho::new(1, 2, 4, 5);
-        let l_two = Ortho::new(2, 3, 5, 6);
-        let left_ortho = Ortho::zip_over(&l_one, &l_two, &btreemap! { 3 => 2, 5 => 4 }, 3);
-        let r_one = Ortho::new(7, 8, 10, 11);
-        let r_two = Ortho::new(8, 9, 11, 12);
-        let r = Ortho::zip_over(&r_one, &r_two, &btreemap! { 9 => 8, 12 => 10 }, 9);
-        let mut pairs = btreemap! { 1 => vec![left_ortho], 7 => vec![r]};
-
-        Ok(pairs.entry(o).or_default().to_owned())
-    }
-
-    fn fake_ortho_by_origin(
-        _conn: Option<&PgConnection>,
-        o: Word,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
-        let mut pairs = btreemap! { 1 => vec![Ortho::new(
-            1,
-            2,
-            3,
-            4,
-        )], 5 => vec![Ortho::new(
-            5,
-            6,
-            7,
-            8,
-        )]};
-        Ok(pairs.entry(o).or_default().to_owned())
-    }
-
-    fn fake_ortho_by_hop(
-        _conn: Option<&PgConnection>,
-        o: Vec<Word>,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
-        let mut ans = vec![];
-
-        if o.contains(&2) {
-            ans.push(Ortho::new(1, 2, 3, 4))
-        }
-
-        if o.contains(&6) {
-            ans.push(Ortho::new(5, 6, 7, 8))
-        }
-
-        Ok(ans)
-    }
-
-    fn fake_ortho_by_contents(
-        _conn: Option<&PgConnection>,
-        o: Vec<Word>,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
-        let mut ans = vec![];
-
-        if o.contains(&4) {
-            ans.push(Ortho::new(1, 2, 3, 4))
-        }
-
-        if o.contains(&8) {
-            ans.push(Ortho::new(5, 6, 7, 8))
-        }
-
-        Ok(ans)
-    }
-
-    fn fake_get_hashes_and_words_of_pairs_with_words_in(
-        _conn: Option<&PgConnection>,
-        _first_words: HashSet<Word>,
-        _second_words: HashSet<Word>,
-    ) -> Result<(HashSet<Word>, HashSet<Word>, HashSet<i64>), anyhow::Error> {
-        let pairs = vec![
+    // a b   e f
+    // c d   g h
+    fn cube_pairs() -> Vec<(Word, Word)> {
+        vec![
             (1, 2),
             (3, 4),
             (1, 3),
@@ -328,53 +188,15 @@ mod tests {
             (2, 6),
             (3, 7),
             (4, 8),
-        ];
-        let res = pairs.iter().map(|(l, r)| ints_to_big_int(*l, *r)).collect();
-        Ok((
-            hashset! {1, 3, 2, 5, 7, 6, 4},
-            hashset! {2, 4, 3, 6, 8, 7, 5},
-            res,
-        ))
+        ]
     }
 
-    fn fake_get_hashes_and_words_of_pairs_with_words_in_two(
-        _conn: Option<&PgConnection>,
-        _first_words: HashSet<Word>,
-        _second_words: HashSet<Word>,
-    ) -> Result<(HashSet<Word>, HashSet<Word>, HashSet<i64>), anyhow::Error> {
-        let pairs = vec![
-            (1, 2),
-            (3, 4),
-            (1, 3),
-            (2, 4),
-            (5, 6),
-            (7, 8),
-            (5, 7),
-            (6, 8),
-            (1, 5),
-            (2, 6),
-            (3, 7),
-        ];
-        let res = pairs.iter().map(|(l, r)| ints_to_big_int(*l, *r)).collect();
-        Ok((
-            hashset! {1, 3, 2, 5, 7, 6},
-            hashset! {2, 4, 3, 6, 8, 7, 5},
-            res,
-        ))
+    fn two_squares() -> Vec<Ortho> {
+        vec![Ortho::new(1, 2, 3, 4), Ortho::new(5, 6, 7, 8)]
     }
 
-    #[test]
-    fn it_creates_up_on_pair_add_when_origin_points_to_origin() {
-        let actual = up_by_origin(
-            None,
-            1,
-            5,
-            fake_ortho_by_origin,
-            fake_get_hashes_and_words_of_pairs_with_words_in,
-        )
-        .unwrap();
-
-        let expected = Ortho::zip_up(
+    fn cube() -> Ortho {
+        Ortho::zip_up(
             &Ortho::new(1, 2, 3, 4),
             &Ortho::new(5, 6, 7, 8),
             &btreemap! {
@@ -382,63 +204,72 @@ mod tests {
                 6 => 2,
                 7 => 3
             },
-        );
+        )
+    }
 
-        assert_eq!(actual, vec![expected]);
+    #[test]
+    fn it_creates_up_on_pair_add_when_origin_points_to_origin() {
+        let store = InMemoryFactStore::from_facts(two_squares(), cube_pairs(), vec![]);
+
+        let actual = up_by_origin(&store, 1, 5).unwrap();
+
+        assert_eq!(actual, vec![cube()]);
     }
 
     #[test]
     fn it_does_not_create_up_when_a_forward_is_missing() {
-        let actual = up_by_origin(
-            None,
-            1,
-            5,
-            fake_ortho_by_origin,
-            fake_get_hashes_and_words_of_pairs_with_words_in_two,
-        )
-        .unwrap();
+        let pairs = cube_pairs().into_iter().filter(|p| *p != (4, 8)).collect();
+        let store = InMemoryFactStore::from_facts(two_squares(), pairs, vec![]);
+
+        let actual = up_by_origin(&store, 1, 5).unwrap();
 
         assert_eq!(actual, vec![]);
     }
 
     #[test]
     fn it_does_not_produce_up_if_that_would_create_a_diagonal_conflict() {
-        let actual = up_by_origin(
-            None,
-            1,
-            5,
-            fake_ortho_by_origin_two,
-            fake_get_hashes_and_words_of_pairs_with_words_in,
-        )
-        .unwrap();
+        let store = InMemoryFactStore::from_facts(
+            vec![Ortho::new(1, 2, 3, 3), Ortho::new(5, 6, 3, 8)],
+            cube_pairs(),
+            vec![],
+        );
+
+        let actual = up_by_origin(&store, 1, 5).unwrap();
 
         assert_eq!(actual, vec![]);
     }
 
     #[test]
     fn it_does_not_produce_up_for_non_base_dims_even_if_eligible() {
-        let actual = up_by_origin(
-            None,
-            1,
-            7,
-            fake_ortho_by_origin_three,
-            fake_get_hashes_and_words_of_pairs_with_words_in,
-        )
-        .unwrap();
+        let l_one = Ortho::new(1, 2, 4, 5);
+        let l_two = Ortho::new(2, 3, 5, 6);
+        let left_ortho = Ortho::zip_over(&l_one, &l_two, &btreemap! { 3 => 2, 5 => 4 }, 3);
+        let r_one = Ortho::new(7, 8, 10, 11);
+        let r_two = Ortho::new(8, 9, 11, 12);
+        let r = Ortho::zip_over(&r_one, &r_two, &btreemap! { 9 => 8, 11 => 10 }, 9);
+        let store = InMemoryFactStore::from_facts(
+            vec![left_ortho, r],
+            vec![(1, 7), (2, 8), (3, 9), (4, 10), (5, 11), (6, 12)],
+            vec![],
+        );
+
+        let actual = up_by_origin(&store, 1, 7).unwrap();
 
         assert_eq!(actual, vec![]);
     }
 
     #[test]
     fn it_only_attempts_to_combine_same_dim_orthos() {
-        let actual = up_by_origin(
-            None,
-            1,
-            5,
-            fake_ortho_by_origin_four,
-            fake_get_hashes_and_words_of_pairs_with_words_in,
-        )
-        .unwrap();
+        let l_one = Ortho::new(5, 6, 7, 8);
+        let r_one = Ortho::new(9, 10, 11, 12);
+        let combined = Ortho::zip_up(&l_one, &r_one, &btreemap! { 10 => 6, 11 => 7 });
+        let store = InMemoryFactStore::from_facts(
+            vec![Ortho::new(1, 2, 3, 4), combined],
+            cube_pairs(),
+            vec![],
+        );
+
+        let actual = up_by_origin(&store, 1, 5).unwrap();
 
         assert_eq!(actual, vec![]);
     }
@@ -446,50 +277,20 @@ mod tests {
     #[test]
     fn it_attempts_to_combine_by_hop() {
         // same combine as before, but b -> f is the pair so it must index into hops
-        let actual = up_by_hop(
-            None,
-            2, // a b c d + e f g h
-            6,
-            fake_ortho_by_hop,
-            fake_get_hashes_and_words_of_pairs_with_words_in,
-        )
-        .unwrap();
+        let store = InMemoryFactStore::from_facts(two_squares(), cube_pairs(), vec![]);
 
-        let expected = Ortho::zip_up(
-            &Ortho::new(1, 2, 3, 4),
-            &Ortho::new(5, 6, 7, 8),
-            &btreemap! {
-                5 => 1,
-                6 => 2,
-                7 => 3
-            },
-        );
+        let actual = up_by_hop(&store, 2, 6).unwrap();
 
-        assert_eq!(actual, vec![expected]);
+        assert_eq!(actual, vec![cube()]);
     }
 
     #[test]
     fn it_attempts_to_combine_by_contents() {
         // same combine as before, but d -> h is the pair so it must index into contents
-        let actual = up_by_contents(
-            None,
-            4, // a b c d + e f g h
-            8,
-            fake_ortho_by_contents,
-            fake_get_hashes_and_words_of_pairs_with_words_in,
-        )
-        .unwrap();
+        let store = InMemoryFactStore::from_facts(two_squares(), cube_pairs(), vec![]);
 
-        let expected = Ortho::zip_up(
-            &Ortho::new(1, 2, 3, 4),
-            &Ortho::new(5, 6, 7, 8),
-            &btreemap! {
-                5 => 1,
-                6 => 2,
-                7 => 3
-            },
-        );
+        let actual = up_by_contents(&store, 4, 8).unwrap();
 
-        assert_eq!(actual, vec![expected]);
+        assert_eq!(actual, vec![cube()]);
     }
 }
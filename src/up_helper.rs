@@ -41,11 +41,11 @@ fn mapping_is_complete(
     lo: &Ortho,
     ro: &Ortho,
 ) -> bool {
-    for (right_location, right_name) in &ro.info {
+    for (right_location, right_name) in ro.to_vec() {
         if right_location.length() > 1 {
             let mapped = right_location.map_location(mapping);
             let left_name = lo.name_at_location(&mapped);
-            if !all_pairs.contains(&ints_to_big_int(left_name, *right_name)) {
+            if !all_pairs.contains(&ints_to_big_int(left_name, right_name)) {
                 return false;
             }
         }
@@ -1,38 +1,27 @@
-use crate::{ortho::Ortho, up_helper, FailableHashsetWordsToHashsetNumbers, Word, FailableWordToVecOfOrthosfn};
-use anyhow::{Error, Ok};
-use diesel::PgConnection;
+use crate::{fact_store::FactStore, ortho::Ortho, up_helper, Word};
+use anyhow::Ok;
 use std::collections::HashSet;
 
-#[tracing::instrument(
-    level = "info",
-    skip(
-        conn,
-        get_ortho_by_origin_batch,
-        forward,
-        get_pair_hashes_relevant_to_vocabularies
-    )
-)]
+#[tracing::instrument(level = "info", skip(store))]
 pub(crate) fn up_forward(
-    conn: Option<&PgConnection>,
+    store: &impl FactStore,
     old_ortho: Ortho,
-    get_ortho_by_origin_batch: FailableWordToVecOfOrthosfn,
-    forward: fn(Option<&PgConnection>, Word) -> Result<HashSet<Word>, Error>,
-    get_pair_hashes_relevant_to_vocabularies: FailableHashsetWordsToHashsetNumbers,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
     if !old_ortho.is_base() {
         return Ok(vec![]);
     }
     let mut ans = vec![];
 
-    let projected_forward = forward(conn, old_ortho.get_origin())?;
-    let orthos_to_right: Vec<Ortho> = get_ortho_by_origin_batch(conn, projected_forward)?
+    let projected_forward = store.project_forward(old_ortho.get_origin())?;
+    let orthos_to_right: Vec<Ortho> = store
+        .get_ortho_by_origin_batch(projected_forward)?
         .iter()
         .filter(|o| old_ortho.get_dims() == o.get_dims())
         .cloned()
         .collect();
 
     if orthos_to_right.is_empty() {
-        return Ok(vec![])
+        return Ok(vec![]);
     }
 
     let forward_left_vocab: HashSet<Word> =
@@ -44,7 +33,7 @@ pub(crate) fn up_forward(
         .collect();
 
     let forward_hashes =
-        get_pair_hashes_relevant_to_vocabularies(conn, forward_left_vocab, forward_right_vocab)?;
+        store.get_hashes_of_pairs_with_words_in(forward_left_vocab, forward_right_vocab)?;
 
     for ro in orthos_to_right {
         for answer in up_helper::attempt_up(&forward_hashes, &old_ortho, &ro) {
@@ -56,11 +45,8 @@ pub(crate) fn up_forward(
 }
 
 pub(crate) fn up_back(
-    conn: Option<&PgConnection>,
+    store: &impl FactStore,
     old_ortho: Ortho,
-    get_ortho_by_origin_batch: FailableWordToVecOfOrthosfn,
-    backward: fn(Option<&PgConnection>, Word) -> Result<HashSet<Word>, Error>,
-    get_pair_hashes_relevant_to_vocabularies: FailableHashsetWordsToHashsetNumbers,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
     if !old_ortho.is_base() {
         return Ok(vec![]);
@@ -68,15 +54,16 @@ pub(crate) fn up_back(
 
     let mut ans = vec![];
 
-    let projected_backward = backward(conn, old_ortho.get_origin())?;
+    let projected_backward = store.project_backward(old_ortho.get_origin())?;
 
-    let orthos_to_left: Vec<Ortho> = get_ortho_by_origin_batch(conn, projected_backward)?
+    let orthos_to_left: Vec<Ortho> = store
+        .get_ortho_by_origin_batch(projected_backward)?
         .into_iter()
         .filter(|o| old_ortho.get_dims() == o.get_dims())
         .collect();
 
     if orthos_to_left.is_empty() {
-        return Ok(vec![])
+        return Ok(vec![]);
     }
 
     let backward_left_vocab = orthos_to_left
@@ -87,7 +74,7 @@ pub(crate) fn up_back(
 
     let backward_right_vocab = old_ortho.to_vec().into_iter().map(|(_l, r)| r).collect();
     let backward_hashes =
-        get_pair_hashes_relevant_to_vocabularies(conn, backward_left_vocab, backward_right_vocab)?;
+        store.get_hashes_of_pairs_with_words_in(backward_left_vocab, backward_right_vocab)?;
 
     for lo in orthos_to_left {
         for answer in up_helper::attempt_up(&backward_hashes, &lo, &old_ortho) {
@@ -101,59 +88,32 @@ pub(crate) fn up_back(
 #[cfg(test)]
 mod tests {
     use crate::{
-        ints_to_big_int, ortho::Ortho, up_on_ortho_found_handler::up_back,
-        up_on_ortho_found_handler::up_forward, Word,
+        fact_store::InMemoryFactStore, ortho::Ortho, up_on_ortho_found_handler::up_back,
+        up_on_ortho_found_handler::up_forward,
     };
-    use diesel::PgConnection;
-    use maplit::{btreemap, hashset};
-    use std::collections::HashSet;
-
-    fn fake_forward(
-        _conn: Option<&PgConnection>,
-        from: Word,
-    ) -> Result<HashSet<Word>, anyhow::Error> {
-        let mut pairs = btreemap! { 1 => hashset! {12, 2, 3, 5}, 2 => hashset! {4, 11}, 3 => hashset! {4, 5}, 4 => hashset! {11}, 5 => hashset! {11, 12}, 6 => hashset! {13}, 7 => hashset! {13}};
-        Ok(pairs.entry(from).or_default().to_owned())
-    }
-
-    fn fake_backward(
-        _conn: Option<&PgConnection>,
-        from: Word,
-    ) -> Result<HashSet<Word>, anyhow::Error> {
-        let mut pairs = btreemap! { 2 => hashset! {1}, 3 => hashset! {1}, 4 => hashset! {2, 3}, 5 => hashset! {1}, 6 => hashset! {5, 4}, 7 => hashset! {5, 3}, 8 => hashset! {11, 12, 4}};
-        Ok(pairs.entry(from).or_default().to_owned())
-    }
-
-    fn fake_ortho_by_origin_batch(
-        _conn: Option<&PgConnection>,
-        _o: HashSet<Word>,
-    ) -> Result<Vec<Ortho>, anyhow::Error> {
-        let os = vec![Ortho::new(1, 2, 3, 4), Ortho::new(5, 6, 7, 8)];
-
-        Ok(os)
-    }
-
-    fn fake_pair_hash_db_filter(
-        _conn: Option<&PgConnection>,
-        _first_words: HashSet<Word>,
-        _second_words: HashSet<Word>,
-    ) -> Result<HashSet<i64>, anyhow::Error> {
-        let pairs = vec![
-            (1, 2),
-            (3, 4),
-            (1, 3),
-            (2, 4),
-            (5, 6),
-            (7, 8),
-            (5, 7),
-            (6, 8),
-            (1, 5),
-            (2, 6),
-            (3, 7),
-            (4, 8),
-        ];
-        let res = pairs.iter().map(|(l, r)| ints_to_big_int(*l, *r)).collect();
-        Ok(res)
+    use maplit::btreemap;
+
+    // a b   e f
+    // c d   g h
+    fn store() -> InMemoryFactStore {
+        InMemoryFactStore::from_facts(
+            vec![Ortho::new(1, 2, 3, 4), Ortho::new(5, 6, 7, 8)],
+            vec![
+                (1, 2),
+                (3, 4),
+                (1, 3),
+                (2, 4),
+                (5, 6),
+                (7, 8),
+                (5, 7),
+                (6, 8),
+                (1, 5),
+                (2, 6),
+                (3, 7),
+                (4, 8),
+            ],
+            vec![],
+        )
     }
 
     #[test]
@@ -162,14 +122,7 @@ mod tests {
 
         let right_ortho = Ortho::new(5, 6, 7, 8);
 
-        let actual = up_forward(
-            None,
-            left_ortho.clone(),
-            fake_ortho_by_origin_batch,
-            fake_forward,
-            fake_pair_hash_db_filter,
-        )
-        .unwrap();
+        let actual = up_forward(&store(), left_ortho.clone()).unwrap();
         let expected = Ortho::zip_up(
             &left_ortho,
             &right_ortho,
@@ -189,14 +142,7 @@ mod tests {
 
         let right_ortho = Ortho::new(5, 6, 7, 8);
 
-        let actual = up_back(
-            None,
-            right_ortho.clone(),
-            fake_ortho_by_origin_batch,
-            fake_backward,
-            fake_pair_hash_db_filter,
-        )
-        .unwrap();
+        let actual = up_back(&store(), right_ortho.clone()).unwrap();
         let expected = Ortho::zip_up(
             &left_ortho,
             &right_ortho,
@@ -216,14 +162,7 @@ mod tests {
         let l_two = Ortho::new(2, 3, 5, 11);
         let l = Ortho::zip_over(&l_one, &l_two, &btreemap! { 3 => 2, 5 => 4 }, 3);
 
-        let actual = up_forward(
-            None,
-            l,
-            fake_ortho_by_origin_batch,
-            fake_forward,
-            fake_pair_hash_db_filter,
-        )
-        .unwrap();
+        let actual = up_forward(&store(), l).unwrap();
 
         assert_eq!(actual, vec![]);
     }
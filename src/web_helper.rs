@@ -1,17 +1,33 @@
 use std::{
-    collections::{BTreeMap, HashSet},
-    env,
+    collections::{BTreeMap, HashMap, HashSet},
+    env, fmt,
+    str::FromStr,
+    sync::Mutex,
 };
 
 use crate::{
-    create_todo_entry, establish_connection_safe, get_relevant_vocabulary_reverse,
+    book_todo_handler::split_text_to_sentences,
+    collisions, create_todo_entry,
+    fact_store::FactStore,
+    get_relevant_vocabulary, get_relevant_vocabulary_reverse,
     models::NewBook,
-    ortho::Ortho,
+    ortho::{dims_to_shape, Ortho},
+    ortho_encoding,
+    ortho_render::{self, Layout},
+    queue::{self, DeadLetter},
     schema::{self, books, phrases},
-    Book, NewTodo, Word,
+    todo_domain::TodoDomain,
+    vec_of_words_to_big_int, Book, NewTodo, Word,
 };
-use amiquip::{AmqpValue, FieldTable, QueueDeclareOptions};
-use diesel::{PgConnection, QueryDsl, RunQueryDsl};
+use amiquip::{Channel, Connection};
+use anyhow::Context;
+use diesel::{
+    BoolExpressionMethods, ExpressionMethods, PgArrayExpressionMethods, PgConnection, QueryDsl,
+    RunQueryDsl,
+};
+use itertools::Itertools;
+use maplit::hashset;
+use serde::Serialize;
 
 pub fn create_book(
     conn: &PgConnection,
@@ -21,7 +37,7 @@ pub fn create_book(
     conn.build_transaction().serializable().run(|| {
         let book = create_book_entry(conn, title, body)?;
         let to_insert = vec![NewTodo {
-            domain: "books".to_owned(),
+            domain: TodoDomain::Books,
             other: book.id,
         }];
         create_todo_entry(conn, to_insert)?;
@@ -39,160 +55,537 @@ fn create_book_entry(
         .get_result(conn)
 }
 
-pub fn show_books() -> Result<String, anyhow::Error> {
+pub fn show_books(conn: &PgConnection) -> Result<Vec<String>, anyhow::Error> {
     use crate::books;
     use crate::diesel::query_dsl::select_dsl::SelectDsl;
-    let results: Vec<String> =
-        SelectDsl::select(books, schema::books::title).load(&establish_connection_safe()?)?;
+    let results: Vec<String> = SelectDsl::select(books, schema::books::title).load(conn)?;
 
-    Ok(results.join("\n"))
+    Ok(results)
 }
 
-pub fn show_todos() -> Result<String, anyhow::Error> {
+pub fn show_todos(conn: &PgConnection) -> Result<i64, anyhow::Error> {
     use crate::schema::todos::dsl::todos;
-    let results: i64 = todos.count().get_result(&establish_connection_safe()?)?;
+    let results: i64 = todos.count().get_result(conn)?;
 
-    Ok(results.to_string())
+    Ok(results)
 }
 
-pub fn count_sentences() -> Result<String, anyhow::Error> {
+pub fn count_sentences(conn: &PgConnection) -> Result<i64, anyhow::Error> {
     use crate::schema::sentences::dsl::sentences;
-    let results: i64 = sentences
-        .count()
-        .get_result(&establish_connection_safe()?)?;
+    let results: i64 = sentences.count().get_result(conn)?;
 
-    Ok(results.to_string())
+    Ok(results)
 }
 
-pub fn count_pairs() -> Result<String, anyhow::Error> {
+pub fn count_pairs(conn: &PgConnection) -> Result<i64, anyhow::Error> {
     use crate::schema::pairs::dsl::pairs;
-    let results: i64 = pairs.count().get_result(&establish_connection_safe()?)?;
+    let results: i64 = pairs.count().get_result(conn)?;
 
-    Ok(results.to_string())
+    Ok(results)
 }
 
-pub fn splat_pairs() -> Result<String, anyhow::Error> {
-    use crate::schema::pairs::dsl::pairs;
-    let results: Vec<crate::models::Pair> = pairs
-        .select(schema::pairs::all_columns)
-        .get_results(&establish_connection_safe()?)?;
-    let res: Vec<String> = results
-        .iter()
-        .map(|p| format!("{}, {}", p.first_word, p.second_word))
-        .collect();
+pub const DEFAULT_PAGE_SIZE: i64 = 100;
+pub const MAX_PAGE_SIZE: i64 = 1000;
 
-    Ok(res.join("\n"))
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    Ascending,
+    Descending,
 }
 
-pub fn show_orthos(dims: BTreeMap<usize, usize>) -> Result<String, anyhow::Error> {
-    let results = get_orthos_by_size(&establish_connection_safe()?, dims)?;
+impl FromStr for Order {
+    type Err = anyhow::Error;
 
-    let res = results.len().to_string();
+    fn from_str(s: &str) -> Result<Order, anyhow::Error> {
+        match s {
+            "asc" => Ok(Order::Ascending),
+            "desc" => Ok(Order::Descending),
+            other => anyhow::bail!("{:?} is not an order, expected asc or desc", other),
+        }
+    }
+}
 
-    Ok(res)
+// Rows are paged in id order, which is the order they were found in. `after` is the `next` of
+// the previous page, and a page with no `next` is the last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageRequest {
+    pub after: Option<i32>,
+    pub limit: i64,
+    pub order: Order,
 }
 
-pub fn splat_orthos(dims: BTreeMap<usize, usize>) -> Result<String, anyhow::Error> {
-    let results = get_orthos_by_size(&establish_connection_safe()?, dims)?;
+impl PageRequest {
+    pub fn new(
+        after: Option<i32>,
+        limit: Option<i64>,
+        order: Option<Order>,
+    ) -> Result<PageRequest, anyhow::Error> {
+        let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        anyhow::ensure!(
+            (1..=MAX_PAGE_SIZE).contains(&limit),
+            "limit must be between 1 and {}",
+            MAX_PAGE_SIZE
+        );
+        Ok(PageRequest {
+            after,
+            limit,
+            order: order.unwrap_or(Order::Ascending),
+        })
+    }
+
+    // Takes rows loaded with a limit of one more than the page, so that the extra row tells
+    // whether there is another page.
+    fn page<T>(&self, mut rows: Vec<(i32, T)>) -> Page<T> {
+        let mut next = None;
+        if rows.len() as i64 > self.limit {
+            rows.truncate(self.limit as usize);
+            next = rows.last().map(|(id, _)| *id);
+        }
+        Page {
+            items: rows.into_iter().map(|(_, item)| item).collect(),
+            next,
+        }
+    }
+}
 
-    let phrases: Vec<_> = results
+#[derive(Debug, PartialEq, Eq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownWord(pub String);
+
+impl fmt::Display for UnknownWord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no sentence has had the word {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownWord {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownPhrase(pub String);
+
+impl fmt::Display for UnknownPhrase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "no sentence has had the phrase {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownPhrase {}
+
+fn find_word(conn: &PgConnection, word: &str) -> Result<Word, anyhow::Error> {
+    let found = get_relevant_vocabulary(conn, hashset! {word.to_owned()})?;
+    Ok(*found
+        .get(word)
+        .ok_or_else(|| UnknownWord(word.to_owned()))?)
+}
+
+fn to_names(
+    conn: &PgConnection,
+    words: HashSet<Word>,
+) -> Result<impl Fn(&Word) -> String, anyhow::Error> {
+    let mapping = get_relevant_vocabulary_reverse(conn, words)?;
+    Ok(move |w: &Word| mapping.get(w).expect("do not look up new words").clone())
+}
+
+// Pairs as words, optionally only those with `word` on either side.
+pub fn splat_pairs(
+    conn: &PgConnection,
+    word: Option<&str>,
+    page: PageRequest,
+) -> Result<Page<(String, String)>, anyhow::Error> {
+    use crate::schema::pairs::{first_word, id, second_word, table as pairs};
+    let mut query = pairs.select((id, first_word, second_word)).into_boxed();
+    if let Some(word) = word {
+        let word = find_word(conn, word)?;
+        query = query.filter(first_word.eq(word).or(second_word.eq(word)));
+    }
+    query = match (page.order, page.after) {
+        (Order::Ascending, Some(after)) => query.filter(id.gt(after)).order(id.asc()),
+        (Order::Ascending, None) => query.order(id.asc()),
+        (Order::Descending, Some(after)) => query.filter(id.lt(after)).order(id.desc()),
+        (Order::Descending, None) => query.order(id.desc()),
+    };
+    let results: Vec<(i32, Word, Word)> = query.limit(page.limit + 1).load(conn)?;
+
+    let name = to_names(
+        conn,
+        results.iter().flat_map(|(_, f, s)| [*f, *s]).collect(),
+    )?;
+    let res = results
         .iter()
-        .map(|o| o.all_full_length_phrases())
+        .map(|(pair_id, f, s)| (*pair_id, (name(f), name(s))))
         .collect();
 
-    let all_words: HashSet<Word> = phrases.iter().flatten().flatten().cloned().collect();
-    let mapping = get_relevant_vocabulary_reverse(&establish_connection_safe()?, all_words)?;
+    Ok(page.page(res))
+}
+
+pub fn show_orthos(
+    conn: &PgConnection,
+    dims: BTreeMap<usize, usize>,
+) -> Result<i64, anyhow::Error> {
+    use crate::schema::orthotopes::{dims as shape, dsl::orthotopes};
+    let count: i64 = orthotopes
+        .filter(shape.eq(dims_to_shape(&dims)))
+        .count()
+        .get_result(conn)?;
 
+    Ok(count)
+}
+
+// Orthos as their full-length phrases, optionally only those with `word` anywhere in them.
+pub fn splat_orthos(
+    conn: &PgConnection,
+    dims: BTreeMap<usize, usize>,
+    word: Option<&str>,
+    page: PageRequest,
+) -> Result<Page<Vec<Vec<String>>>, anyhow::Error> {
+    use crate::schema::orthotopes::{
+        contents, dims as shape, hop, id, information, origin, table as orthotopes,
+    };
+    let mut query = orthotopes
+        .select((id, information))
+        .filter(shape.eq(dims_to_shape(&dims)))
+        .into_boxed();
+    if let Some(word) = word {
+        let word = find_word(conn, word)?;
+        query = query.filter(
+            origin
+                .eq(word)
+                .or(hop.overlaps_with(vec![word]))
+                .or(contents.overlaps_with(vec![word])),
+        );
+    }
+    query = match (page.order, page.after) {
+        (Order::Ascending, Some(after)) => query.filter(id.gt(after)).order(id.asc()),
+        (Order::Ascending, None) => query.order(id.asc()),
+        (Order::Descending, Some(after)) => query.filter(id.lt(after)).order(id.desc()),
+        (Order::Descending, None) => query.order(id.desc()),
+    };
+    let results: Vec<(i32, Vec<u8>)> = query.limit(page.limit + 1).load(conn)?;
+
+    let phrases = results
+        .iter()
+        .map(|(ortho_id, bytes)| {
+            Ok((
+                *ortho_id,
+                ortho_encoding::decode(bytes)?.all_full_length_phrases(),
+            ))
+        })
+        .collect::<Result<Vec<_>, anyhow::Error>>()?;
+    let name = to_names(
+        conn,
+        phrases
+            .iter()
+            .flat_map(|(_, o)| o.iter().flatten().cloned())
+            .collect(),
+    )?;
     let res = phrases
         .iter()
-        .map(|o| {
-            o.iter()
-                .map(|s| {
-                    s.iter()
-                        .map(|w| mapping.get(w).expect("do not look up new words"))
-                        .cloned()
-                        .collect::<Vec<_>>()
-                        .join(" ")
-                })
-                .collect::<Vec<_>>()
-                .join("\n")
+        .map(|(ortho_id, o)| {
+            let o = o
+                .iter()
+                .map(|phrase| phrase.iter().map(&name).collect())
+                .collect();
+            (*ortho_id, o)
         })
-        .collect::<Vec<_>>()
-        .join("\n\n");
+        .collect();
+
+    Ok(page.page(res))
+}
+
+pub fn render_orthos(
+    conn: &PgConnection,
+    dims: BTreeMap<usize, usize>,
+    layout: Layout,
+) -> Result<Vec<String>, anyhow::Error> {
+    let results = get_orthos_by_size(conn, dims)?;
+
+    let all_words: HashSet<Word> = results.iter().flat_map(|o| o.get_vocabulary()).collect();
+    let mapping = get_relevant_vocabulary_reverse(conn, all_words)?;
+
+    let res = results
+        .iter()
+        .map(|o| ortho_render::render(o, layout, &mapping))
+        .collect::<Result<Vec<_>, _>>()?;
 
     Ok(res)
 }
 
-pub fn show_phrases() -> Result<String, anyhow::Error> {
+// The orthos a word is in, by where it is in them: at the origin, on an axis next to the origin,
+// or anywhere further out.
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ByPosition<T> {
+    pub origin: Vec<T>,
+    pub hop: Vec<T>,
+    pub contents: Vec<T>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+pub struct RenderedOrtho {
+    pub dims: String,
+    pub grid: String,
+}
+
+pub fn orthos_with_word(
+    store: &impl FactStore,
+    word: Word,
+) -> Result<ByPosition<Ortho>, anyhow::Error> {
+    Ok(ByPosition {
+        origin: store.get_ortho_by_origin(word)?,
+        hop: store.get_ortho_by_hop(vec![word])?,
+        contents: store.get_ortho_by_contents(vec![word])?,
+    })
+}
+
+// Every ortho with the word `word` in it, each drawn as by `ortho_render::render`.
+pub fn find_orthos_with_word(
+    conn: &PgConnection,
+    word: &str,
+) -> Result<ByPosition<RenderedOrtho>, anyhow::Error> {
+    let found = orthos_with_word(conn, find_word(conn, word)?)?;
+
+    let all_words: HashSet<Word> = [&found.origin, &found.hop, &found.contents]
+        .into_iter()
+        .flatten()
+        .flat_map(|o| o.get_vocabulary())
+        .collect();
+    let mapping = get_relevant_vocabulary_reverse(conn, all_words)?;
+
+    Ok(ByPosition {
+        origin: rendered(&found.origin, &mapping)?,
+        hop: rendered(&found.hop, &mapping)?,
+        contents: rendered(&found.contents, &mapping)?,
+    })
+}
+
+// Whether a sentence has had `phrase`. Ingestion keeps two-word phrases as pairs rather than in
+// `phrases`.
+pub fn is_known_phrase(store: &impl FactStore, phrase: &[Word]) -> Result<bool, anyhow::Error> {
+    let known = match phrase {
+        [first, second] => {
+            store.get_hashes_of_pairs_with_words_in(hashset! {*first}, hashset! {*second})?
+        }
+        _ => store.get_phrases_with_matching_hashes(
+            hashset! {vec_of_words_to_big_int(phrase.to_vec())},
+        )?,
+    };
+    Ok(!known.is_empty())
+}
+
+// The orthos out of `candidates` with `phrase` as one of their full-length phrases.
+pub fn with_full_length_phrase(candidates: Vec<Ortho>, phrase: &[Word]) -> Vec<Ortho> {
+    candidates
+        .into_iter()
+        .filter(|o| {
+            o.all_full_length_phrases()
+                .iter()
+                .any(|p| p.as_slice() == phrase)
+        })
+        .collect()
+}
+
+// Orthos with every one of `words` in them, as the origin, in the hop or in the contents. Each word
+// narrows the one query through the indexes on those columns, so a phrase of common words only
+// loads the orthos that could have it.
+fn get_orthos_with_every_word(
+    conn: &PgConnection,
+    words: &[Word],
+) -> Result<Vec<Ortho>, anyhow::Error> {
+    use crate::schema::orthotopes::{contents, hop, information, origin, table as orthotopes};
+    let mut query = orthotopes.select(information).into_boxed();
+    for word in words.iter().unique() {
+        query = query.filter(
+            origin
+                .eq(*word)
+                .or(hop.overlaps_with(vec![*word]))
+                .or(contents.overlaps_with(vec![*word])),
+        );
+    }
+    let results: Vec<Vec<u8>> = query.load(conn)?;
+    ortho_encoding::decode_all(&results)
+}
+
+// Every ortho with the phrase `words`, as split by `parse_web_phrase`, along one of its axes.
+pub fn find_orthos_with_phrase(
+    conn: &PgConnection,
+    words: &[String],
+) -> Result<Vec<RenderedOrtho>, anyhow::Error> {
+    let vocabulary = get_relevant_vocabulary(conn, words.iter().cloned().collect())?;
+    let phrase = words
+        .iter()
+        .map(|word| {
+            vocabulary
+                .get(word)
+                .copied()
+                .ok_or_else(|| UnknownWord(word.clone()))
+        })
+        .collect::<Result<Vec<Word>, _>>()?;
+    if !is_known_phrase(conn, &phrase)? {
+        return Err(UnknownPhrase(words.join(" ")).into());
+    }
+    let found = with_full_length_phrase(get_orthos_with_every_word(conn, &phrase)?, &phrase);
+
+    let all_words: HashSet<Word> = found.iter().flat_map(|o| o.get_vocabulary()).collect();
+    let mapping = get_relevant_vocabulary_reverse(conn, all_words)?;
+    rendered(&found, &mapping)
+}
+
+fn rendered(
+    orthos: &[Ortho],
+    mapping: &HashMap<Word, String>,
+) -> Result<Vec<RenderedOrtho>, anyhow::Error> {
+    orthos
+        .iter()
+        .map(|o| {
+            Ok(RenderedOrtho {
+                dims: o.get_shape(),
+                grid: ortho_render::render(o, Layout::default(), mapping)?,
+            })
+        })
+        .collect()
+}
+
+pub fn show_phrases(conn: &PgConnection) -> Result<i64, anyhow::Error> {
     use crate::schema::phrases::dsl::phrases;
-    let results: i64 = phrases.count().get_result(&establish_connection_safe()?)?;
+    let results: i64 = phrases.count().get_result(conn)?;
 
-    Ok(results.to_string())
+    Ok(results)
+}
+
+pub fn show_collisions(conn: &PgConnection) -> Result<i64, anyhow::Error> {
+    let results = collisions::count_collisions(conn)?;
+
+    Ok(results)
 }
 
 fn get_orthos_by_size(
     conn: &PgConnection,
     dims: BTreeMap<usize, usize>,
 ) -> Result<Vec<Ortho>, anyhow::Error> {
-    use crate::schema::orthotopes::table as orthotopes;
+    use crate::schema::orthotopes::{dims as shape, table as orthotopes};
     let results: Vec<Vec<u8>> = orthotopes
+        .filter(shape.eq(dims_to_shape(&dims)))
         .select(schema::orthotopes::information)
         .load(conn)?;
 
-    let actual: Vec<Ortho> = results
-        .iter()
-        .map(|x| bincode::deserialize(x).expect("deserialization should succeed"))
-        .filter(|o: &Ortho| o.get_dims() == dims)
-        .collect();
+    let actual = ortho_encoding::decode_all(&results)?;
 
     Ok(actual)
 }
 
-pub fn show_depth() -> Result<String, amiquip::Error> {
-    use amiquip::Connection;
+// The web server's connection to RabbitMQ, opened on first use and opened again after any error.
+// Depth checks share one channel on it. Dead letters are read on a channel of their own, because
+// the messages they peek at only return to the queue when that channel closes.
+#[derive(Default)]
+pub struct RabbitConnection {
+    open: Mutex<Option<(Connection, Channel)>>,
+}
 
-    let rabbit_url = env::var("RABBIT_URL").expect("RABBIT_URL must be set");
+impl RabbitConnection {
+    fn with<T>(
+        &self,
+        f: impl FnOnce(&mut Connection, &Channel) -> Result<T, anyhow::Error>,
+    ) -> Result<T, anyhow::Error> {
+        let mut open = self
+            .open
+            .lock()
+            .expect("rabbit connection lock is poisoned");
+        if open.is_none() {
+            let rabbit_url = env::var("RABBIT_URL").context("RABBIT_URL is not set")?;
+            let mut connection = Connection::insecure_open(&rabbit_url)?;
+            let channel = connection.open_channel(None)?;
+            *open = Some((connection, channel));
+        }
+        let (connection, channel) = open.as_mut().expect("connection was just opened");
+        let result = f(connection, channel);
+        if result.is_err() {
+            *open = None;
+        }
+        result
+    }
 
-    let mut connection = Connection::insecure_open(&rabbit_url)?;
+    fn with_own_channel<T>(
+        &self,
+        f: impl FnOnce(&Channel) -> Result<T, amiquip::Error>,
+    ) -> Result<T, anyhow::Error> {
+        self.with(|connection, _shared| {
+            let channel = connection.open_channel(None)?;
+            let result = f(&channel)?;
+            channel.close()?;
+            Ok(result)
+        })
+    }
+}
+
+// A work queue that nothing has declared yet is empty. The broker closes the channel to say so,
+// which `with` has already replaced by the time the error gets here.
+pub fn show_depth(rabbit: &RabbitConnection) -> Result<u32, anyhow::Error> {
+    match rabbit.with(|_connection, channel| Ok(queue::work_depth(channel)?)) {
+        Err(e) if is_not_found(&e) => Ok(0),
+        depth => depth,
+    }
+}
 
-    let channel = connection.open_channel(None)?;
-    let mut arguments = FieldTable::new();
-    arguments.insert("x-max-priority".to_string(), AmqpValue::ShortInt(20));
+fn is_not_found(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<amiquip::Error>(),
+        Some(amiquip::Error::ServerClosedChannel { code: 404, .. })
+    )
+}
 
-    let queue = channel.queue_declare(
-        "work",
-        QueueDeclareOptions {
-            durable: true,
-            arguments,
-            ..QueueDeclareOptions::default()
-        },
-    )?;
+pub fn show_dead_letters(rabbit: &RabbitConnection) -> Result<Vec<DeadLetter>, anyhow::Error> {
+    rabbit.with_own_channel(queue::list_dead_letters)
+}
 
-    let depth = queue
-        .declared_message_count()
-        .expect("queue must be declared non-immediate");
-    Ok(depth.to_string())
+pub fn redrive_dead_letters(rabbit: &RabbitConnection) -> Result<usize, anyhow::Error> {
+    rabbit.with_own_channel(queue::redrive_dead_letters)
 }
 
-pub fn parse_web_dims(web_dims_str: String) -> BTreeMap<usize, usize> {
+// Parses dims such as "1,1,2", the length of each axis. Every length must be at least 1.
+pub fn parse_web_dims(web_dims_str: &str) -> Result<BTreeMap<usize, usize>, anyhow::Error> {
     let nums: Vec<usize> = web_dims_str
         .split(',')
         .map(|s| s.trim())
         .filter(|s| !s.is_empty())
-        .map(|s| s.parse().unwrap())
-        .collect();
+        .map(|s| match s.parse() {
+            Ok(num) if num > 0 => Ok(num),
+            _ => Err(anyhow::anyhow!("{:?} is not an axis length", s)),
+        })
+        .collect::<Result<_, _>>()?;
+    anyhow::ensure!(!nums.is_empty(), "dims must list at least one axis length");
 
     let mut res = BTreeMap::default();
     for num in nums {
         *res.entry(num).or_insert(0) += 1
     }
-    res
+    Ok(res)
+}
+
+// Splits a phrase into words the way books are split when they are ingested, so "The old man!"
+// is ["the", "old", "man"]. A phrase must be one sentence of at least two words.
+pub fn parse_web_phrase(web_phrase: &str) -> Result<Vec<String>, anyhow::Error> {
+    let sentences = split_text_to_sentences(web_phrase);
+    anyhow::ensure!(sentences.len() <= 1, "a phrase cannot span sentences");
+    let words: Vec<String> = sentences
+        .iter()
+        .flat_map(|sentence| sentence.split_ascii_whitespace())
+        .map(|word| word.to_owned())
+        .filter(|word| !word.is_empty())
+        .collect();
+    anyhow::ensure!(words.len() >= 2, "a phrase must have at least two words");
+    Ok(words)
 }
 
 pub fn delete_db(conn: &PgConnection) -> Result<(), anyhow::Error> {
     use crate::books;
     use crate::pairs;
+    use crate::schema::hash_collisions::dsl::hash_collisions;
     use crate::schema::orthotopes::dsl::orthotopes;
+    use crate::schema::processed_todos::dsl::processed_todos;
     use crate::sentences::dsl::sentences;
     use crate::todos::dsl::todos;
     use crate::web_helper::phrases::dsl::phrases;
@@ -203,5 +596,117 @@ pub fn delete_db(conn: &PgConnection) -> Result<(), anyhow::Error> {
     diesel::delete(pairs).execute(conn)?;
     diesel::delete(orthotopes).execute(conn)?;
     diesel::delete(phrases).execute(conn)?;
+    diesel::delete(hash_collisions).execute(conn)?;
+    diesel::delete(processed_todos).execute(conn)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use maplit::btreemap;
+
+    use crate::{
+        fact_store::InMemoryFactStore,
+        ortho::Ortho,
+        web_helper::{
+            is_known_phrase, orthos_with_word, parse_web_phrase, with_full_length_phrase, Order,
+            Page, PageRequest, MAX_PAGE_SIZE,
+        },
+    };
+
+    #[test]
+    fn it_checks_the_page_size_and_order() {
+        let default = PageRequest::new(None, None, None).unwrap();
+        assert_eq!(default.limit, 100);
+        assert_eq!(default.order, Order::Ascending);
+
+        assert!(PageRequest::new(None, Some(0), None).is_err());
+        assert!(PageRequest::new(None, Some(MAX_PAGE_SIZE + 1), None).is_err());
+        assert_eq!("desc".parse::<Order>().unwrap(), Order::Descending);
+        assert!("down".parse::<Order>().is_err());
+    }
+
+    #[test]
+    fn it_finds_orthos_by_where_a_word_is_in_them() {
+        // a b   b e
+        // c d   d f
+        let (abcd, bedf) = (Ortho::new(1, 2, 3, 4), Ortho::new(2, 5, 4, 6));
+        let store = InMemoryFactStore::from_facts(vec![abcd.clone(), bedf.clone()], vec![], vec![]);
+
+        let found = orthos_with_word(&store, 2).unwrap();
+        assert_eq!(found.origin, vec![bedf.clone()]);
+        assert_eq!(found.hop, vec![abcd.clone()]);
+        assert!(found.contents.is_empty());
+
+        let found = orthos_with_word(&store, 4).unwrap();
+        assert!(found.origin.is_empty());
+        assert_eq!(found.hop, vec![bedf]);
+        assert_eq!(found.contents, vec![abcd]);
+    }
+
+    #[test]
+    fn it_finds_orthos_along_whose_axes_a_phrase_runs() {
+        // a b c
+        // d e f
+        let abcdef = Ortho::zip_over(
+            &Ortho::new(1, 2, 4, 5),
+            &Ortho::new(2, 3, 5, 6),
+            &btreemap! { 3 => 2, 5 => 4 },
+            3,
+        );
+        let abde = Ortho::new(1, 2, 4, 5);
+        let candidates = vec![abcdef.clone(), abde];
+
+        assert_eq!(
+            with_full_length_phrase(candidates.clone(), &[4, 5, 6]),
+            vec![abcdef.clone()]
+        );
+        assert_eq!(
+            with_full_length_phrase(candidates.clone(), &[2, 5]),
+            candidates
+        );
+        assert!(with_full_length_phrase(candidates, &[1, 2, 5]).is_empty());
+    }
+
+    #[test]
+    fn it_knows_phrases_from_sentences_and_two_word_phrases_from_pairs() {
+        let store =
+            InMemoryFactStore::from_facts(vec![], vec![(2, 5), (1, 5)], vec![vec![4, 5, 6]]);
+
+        assert!(is_known_phrase(&store, &[4, 5, 6]).unwrap());
+        assert!(is_known_phrase(&store, &[2, 5]).unwrap());
+        assert!(!is_known_phrase(&store, &[1, 2, 5]).unwrap());
+        assert!(!is_known_phrase(&store, &[5, 2]).unwrap());
+    }
+
+    #[test]
+    fn it_splits_phrases_as_books_are_split() {
+        assert_eq!(
+            parse_web_phrase(" The old, man!").unwrap(),
+            vec!["the", "old", "man"]
+        );
+        assert!(parse_web_phrase("old. man").is_err());
+        assert!(parse_web_phrase("man").is_err());
+        assert!(parse_web_phrase("").is_err());
+    }
+
+    #[test]
+    fn it_gives_a_next_only_when_there_is_another_page() {
+        let page = PageRequest::new(None, Some(2), None).unwrap();
+
+        assert_eq!(
+            page.page(vec![(3, "a"), (5, "b"), (8, "c")]),
+            Page {
+                items: vec!["a", "b"],
+                next: Some(5)
+            }
+        );
+        assert_eq!(
+            page.page(vec![(3, "a"), (5, "b")]),
+            Page {
+                items: vec!["a", "b"],
+                next: None
+            }
+        );
+    }
+}
@@ -0,0 +1,510 @@
+use std::{collections::BTreeMap, env, str::FromStr, time::Duration};
+
+use anyhow::Context;
+use diesel::r2d2::{ConnectionManager, Pool, PoolError, PooledConnection};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use diesel::{ConnectionError, PgConnection};
+use diesel_migrations::RunMigrationsError;
+use rocket::http::Status;
+use rocket::response::{self, Responder};
+use rocket::serde::json::Json;
+use rocket::{Build, Request, Rocket, State};
+use serde::{Deserialize, Serialize};
+
+use crate::ortho_render::Layout;
+use crate::queue::DeadLetter;
+use crate::web_helper::{
+    self, count_pairs, count_sentences, create_book, redrive_dead_letters, render_orthos,
+    show_books, show_collisions, show_dead_letters, show_depth, show_orthos, show_phrases,
+    show_todos, splat_orthos, splat_pairs, ByPosition, Order, PageRequest, RabbitConnection,
+    RenderedOrtho, UnknownPhrase, UnknownWord,
+};
+
+embed_migrations!("./migrations");
+
+pub fn run_migrations(conn: &PgConnection) -> Result<(), RunMigrationsError> {
+    embedded_migrations::run_with_output(conn, &mut std::io::stdout())
+}
+
+// The body of every error response. `error` is the reason phrase of `status`, e.g. "Bad Request",
+// and `message` says what went wrong.
+#[derive(Serialize, Debug)]
+pub struct ErrorBody {
+    pub status: u16,
+    pub error: String,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub struct ApiError {
+    status: Status,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(error: anyhow::Error) -> ApiError {
+        ApiError {
+            status: Status::BadRequest,
+            message: format!("{:#}", error),
+        }
+    }
+}
+
+// Postgres or RabbitMQ being unreachable or unconfigured is a 503, a row that is already there a 409 and a word or
+// phrase no sentence has had a 404. Anything else is a 500.
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> ApiError {
+        let status = if error.chain().any(is_unreachable) {
+            Status::ServiceUnavailable
+        } else if error.chain().any(is_conflict) {
+            Status::Conflict
+        } else if error
+            .chain()
+            .any(|cause| cause.is::<UnknownWord>() || cause.is::<UnknownPhrase>())
+        {
+            Status::NotFound
+        } else {
+            Status::InternalServerError
+        };
+        ApiError {
+            status,
+            message: format!("{:#}", error),
+        }
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let body = ErrorBody {
+            status: self.status.code,
+            error: self.status.reason_lossy().to_owned(),
+            message: self.message,
+        };
+        (self.status, Json(body)).respond_to(request)
+    }
+}
+
+fn is_unreachable(cause: &(dyn std::error::Error + 'static)) -> bool {
+    use amiquip::Error::*;
+    match cause.downcast_ref::<amiquip::Error>() {
+        Some(error) => matches!(
+            error,
+            FailedToConnect { .. }
+                | ResolveUrlToSocketAddr { .. }
+                | UrlNoSocketAddrs { .. }
+                | ConnectionTimeout
+                | UnexpectedSocketClose
+                | IoErrorReadingSocket { .. }
+                | IoErrorWritingSocket { .. }
+                | MissedServerHeartbeats
+        ),
+        None => {
+            cause.is::<PoolError>()
+                || cause.is::<env::VarError>()
+                || matches!(
+                    cause.downcast_ref::<ConnectionError>(),
+                    Some(ConnectionError::BadConnection(_))
+                )
+        }
+    }
+}
+
+fn is_conflict(cause: &(dyn std::error::Error + 'static)) -> bool {
+    matches!(
+        cause.downcast_ref::<DieselError>(),
+        Some(DieselError::DatabaseError(
+            DatabaseErrorKind::UniqueViolation | DatabaseErrorKind::SerializationFailure,
+            _
+        ))
+    )
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+pub type WebPool = Pool<ConnectionManager<PgConnection>>;
+
+// The connections every route that reads Postgres takes from. The pool is built without
+// connecting, so the server starts while Postgres is down and those routes answer 503 until it is
+// back. WEB_POOL_SIZE (default 10) caps the connections and WEB_POOL_TIMEOUT_MS (default 5000) is
+// how long a route waits for one.
+pub fn web_pool() -> Result<WebPool, anyhow::Error> {
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    let pool_size = match env::var("WEB_POOL_SIZE") {
+        Ok(s) => s.parse()?,
+        Err(_) => 10,
+    };
+    let timeout = match env::var("WEB_POOL_TIMEOUT_MS") {
+        Ok(s) => s.parse()?,
+        Err(_) => 5000,
+    };
+    Ok(Pool::builder()
+        .max_size(pool_size)
+        .connection_timeout(Duration::from_millis(timeout))
+        .build_unchecked(ConnectionManager::new(database_url)))
+}
+
+fn conn(pool: &WebPool) -> Result<PooledConnection<ConnectionManager<PgConnection>>, ApiError> {
+    Ok(pool.get().map_err(anyhow::Error::from)?)
+}
+
+#[derive(Serialize)]
+pub struct Count {
+    pub count: i64,
+}
+
+#[derive(Serialize)]
+pub struct Titles {
+    pub titles: Vec<String>,
+}
+
+// `next` is the `after` that fetches the next page, or null on the last page.
+#[derive(Serialize)]
+pub struct Pairs {
+    pub pairs: Vec<(String, String)>,
+    pub next: Option<i32>,
+}
+
+// Each ortho as its full-length phrases, each phrase as its words.
+#[derive(Serialize)]
+pub struct Phrases {
+    pub orthos: Vec<Vec<Vec<String>>>,
+    pub next: Option<i32>,
+}
+
+// Each ortho drawn as by `ortho_render::render`.
+#[derive(Serialize)]
+pub struct Grids {
+    pub orthos: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct Rendered {
+    pub orthos: Vec<RenderedOrtho>,
+}
+
+#[derive(Serialize)]
+pub struct DeadLetters {
+    pub dead_letters: Vec<DeadLetter>,
+}
+
+#[derive(Serialize)]
+pub struct AddedBook {
+    pub id: i32,
+    pub title: String,
+}
+
+fn count(count: Result<i64, anyhow::Error>) -> ApiResult<Count> {
+    Ok(Json(Count { count: count? }))
+}
+
+fn parse_dims(dims: Option<String>) -> Result<BTreeMap<usize, usize>, ApiError> {
+    let dims = dims.ok_or_else(|| ApiError::bad_request(anyhow::anyhow!("dims is required")))?;
+    web_helper::parse_web_dims(&dims).map_err(ApiError::bad_request)
+}
+
+fn parse<T>(name: &str, value: Option<String>) -> Result<Option<T>, ApiError>
+where
+    T: FromStr,
+    T::Err: Into<anyhow::Error>,
+{
+    value
+        .map(|value| {
+            value
+                .parse()
+                .map_err(Into::into)
+                .with_context(|| format!("{} is {:?}", name, value))
+                .map_err(ApiError::bad_request)
+        })
+        .transpose()
+}
+
+fn parse_page(
+    after: Option<String>,
+    limit: Option<String>,
+    order: Option<String>,
+) -> Result<PageRequest, ApiError> {
+    PageRequest::new(
+        parse("after", after)?,
+        parse("limit", limit)?,
+        parse::<Order>("order", order)?,
+    )
+    .map_err(ApiError::bad_request)
+}
+
+#[get("/")]
+fn index(pool: &State<WebPool>) -> ApiResult<Titles> {
+    Ok(Json(Titles {
+        titles: show_books(&*conn(pool)?)?,
+    }))
+}
+
+#[get("/sentences")]
+fn sentences(pool: &State<WebPool>) -> ApiResult<Count> {
+    count(count_sentences(&*conn(pool)?))
+}
+
+#[get("/pairs")]
+fn pairs(pool: &State<WebPool>) -> ApiResult<Count> {
+    count(count_pairs(&*conn(pool)?))
+}
+
+#[get("/splat-all-pairs?<word>&<after>&<limit>&<order>")]
+fn splat_all_pairs(
+    pool: &State<WebPool>,
+    word: Option<String>,
+    after: Option<String>,
+    limit: Option<String>,
+    order: Option<String>,
+) -> ApiResult<Pairs> {
+    let page = parse_page(after, limit, order)?;
+    let found = splat_pairs(&*conn(pool)?, word.as_deref(), page)?;
+    Ok(Json(Pairs {
+        pairs: found.items,
+        next: found.next,
+    }))
+}
+
+#[get("/count")]
+fn todos(pool: &State<WebPool>) -> ApiResult<Count> {
+    count(show_todos(&*conn(pool)?))
+}
+
+#[get("/depth")]
+fn depth(rabbit: &State<RabbitConnection>) -> ApiResult<Count> {
+    count(show_depth(rabbit).map(i64::from))
+}
+
+#[get("/dead-letters")]
+fn dead_letters(rabbit: &State<RabbitConnection>) -> ApiResult<DeadLetters> {
+    Ok(Json(DeadLetters {
+        dead_letters: show_dead_letters(rabbit)?,
+    }))
+}
+
+#[post("/dead-letters/redrive")]
+fn redrive(rabbit: &State<RabbitConnection>) -> ApiResult<Count> {
+    count(redrive_dead_letters(rabbit).map(|redriven| redriven as i64))
+}
+
+#[get("/phrases")]
+fn phrases(pool: &State<WebPool>) -> ApiResult<Count> {
+    count(show_phrases(&*conn(pool)?))
+}
+
+#[get("/collisions")]
+fn collisions(pool: &State<WebPool>) -> ApiResult<Count> {
+    count(show_collisions(&*conn(pool)?))
+}
+
+#[get("/orthos?<dims>")]
+fn orthos(pool: &State<WebPool>, dims: Option<String>) -> ApiResult<Count> {
+    let dims = parse_dims(dims)?;
+    count(show_orthos(&*conn(pool)?, dims))
+}
+
+#[get("/splat?<dims>&<word>&<after>&<limit>&<order>")]
+fn splat(
+    pool: &State<WebPool>,
+    dims: Option<String>,
+    word: Option<String>,
+    after: Option<String>,
+    limit: Option<String>,
+    order: Option<String>,
+) -> ApiResult<Phrases> {
+    let dims = parse_dims(dims)?;
+    let page = parse_page(after, limit, order)?;
+    let found = splat_orthos(&*conn(pool)?, dims, word.as_deref(), page)?;
+    Ok(Json(Phrases {
+        orthos: found.items,
+        next: found.next,
+    }))
+}
+
+#[get("/render?<dims>&<rows>&<columns>")]
+fn render(
+    pool: &State<WebPool>,
+    dims: Option<String>,
+    rows: Option<String>,
+    columns: Option<String>,
+) -> ApiResult<Grids> {
+    let dims = parse_dims(dims)?;
+    let default = Layout::default();
+    let layout = Layout {
+        rows: parse("rows", rows)?.unwrap_or(default.rows),
+        columns: parse("columns", columns)?.unwrap_or(default.columns),
+    };
+    layout
+        .check(dims.values().sum())
+        .map_err(ApiError::bad_request)?;
+    Ok(Json(Grids {
+        orthos: render_orthos(&*conn(pool)?, dims, layout)?,
+    }))
+}
+
+#[get("/word-orthos?<word>")]
+fn word_orthos(
+    pool: &State<WebPool>,
+    word: Option<String>,
+) -> ApiResult<ByPosition<RenderedOrtho>> {
+    let word = word.ok_or_else(|| ApiError::bad_request(anyhow::anyhow!("word is required")))?;
+    Ok(Json(web_helper::find_orthos_with_word(
+        &*conn(pool)?,
+        &word,
+    )?))
+}
+
+#[get("/phrase-orthos?<phrase>")]
+fn phrase_orthos(pool: &State<WebPool>, phrase: Option<String>) -> ApiResult<Rendered> {
+    let phrase =
+        phrase.ok_or_else(|| ApiError::bad_request(anyhow::anyhow!("phrase is required")))?;
+    let words = web_helper::parse_web_phrase(&phrase).map_err(ApiError::bad_request)?;
+    Ok(Json(Rendered {
+        orthos: web_helper::find_orthos_with_phrase(&*conn(pool)?, &words)?,
+    }))
+}
+
+#[derive(Deserialize)]
+struct WebBook {
+    title: String,
+    body: String,
+}
+
+#[post("/add", format = "json", data = "<web_book>")]
+fn add(pool: &State<WebPool>, web_book: Json<WebBook>) -> ApiResult<AddedBook> {
+    let book = create_book(&*conn(pool)?, web_book.title.clone(), web_book.body.clone())
+        .map_err(anyhow::Error::from)?;
+    Ok(Json(AddedBook {
+        id: book.id,
+        title: book.title,
+    }))
+}
+
+#[delete("/")]
+fn delete(pool: &State<WebPool>) -> Result<Status, ApiError> {
+    web_helper::delete_db(&*conn(pool)?)?;
+    Ok(Status::NoContent)
+}
+
+#[catch(default)]
+fn error(status: Status, request: &Request) -> ApiError {
+    let message = if status == Status::NotFound {
+        format!("nothing at {} {}", request.method(), request.uri())
+    } else {
+        format!("{} {} failed", request.method(), request.uri())
+    };
+    ApiError { status, message }
+}
+
+pub fn build() -> Rocket<Build> {
+    rocket::build()
+        .manage(web_pool().expect("WEB_POOL_SIZE and WEB_POOL_TIMEOUT_MS should be numbers"))
+        .manage(RabbitConnection::default())
+        .register("/", catchers![error])
+        .mount(
+            "/",
+            routes![
+                index,
+                add,
+                todos,
+                depth,
+                sentences,
+                pairs,
+                orthos,
+                delete,
+                phrases,
+                splat,
+                render,
+                word_orthos,
+                phrase_orthos,
+                splat_all_pairs,
+                collisions,
+                dead_letters,
+                redrive
+            ],
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use rocket::serde::json::Value;
+    use rocket::{http::Status, local::blocking::Client};
+
+    use crate::web_server::{build, ApiError};
+
+    fn get(path: &str) -> (Status, Value) {
+        // Nothing listens on port 1, so every connection is refused straight away.
+        env::set_var("DATABASE_URL", "postgres://postgres@127.0.0.1:1/none");
+        env::set_var("RABBIT_URL", "amqp://127.0.0.1:1");
+        env::set_var("WEB_POOL_TIMEOUT_MS", "100");
+        let client = Client::tracked(build()).expect("rocket should build");
+        let response = client.get(path).dispatch();
+        let status = response.status();
+        (status, response.into_json().expect("body should be json"))
+    }
+
+    #[test]
+    fn it_answers_bad_dims_with_400() {
+        let (status, body) = get("/orthos?dims=1,x");
+
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(body["status"], 400);
+        assert_eq!(body["error"], "Bad Request");
+        assert_eq!(body["message"], "\"x\" is not an axis length");
+        assert_eq!(get("/splat").0, Status::BadRequest);
+        assert_eq!(
+            get("/render?dims=1,2&rows=0&columns=0").0,
+            Status::BadRequest
+        );
+        assert_eq!(get("/render?dims=1,2&rows=x").0, Status::BadRequest);
+        assert_eq!(get("/word-orthos").0, Status::BadRequest);
+        assert_eq!(get("/phrase-orthos?phrase=old").0, Status::BadRequest);
+    }
+
+    #[test]
+    fn it_answers_bad_paging_with_400() {
+        let (status, body) = get("/splat-all-pairs?limit=0");
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(body["message"], "limit must be between 1 and 1000");
+
+        let (status, body) = get("/splat?dims=1,1&order=up");
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(
+            body["message"],
+            "order is \"up\": \"up\" is not an order, expected asc or desc"
+        );
+
+        assert_eq!(get("/splat-all-pairs?after=x").0, Status::BadRequest);
+        assert_eq!(get("/splat-all-pairs?limit=1001").0, Status::BadRequest);
+    }
+
+    #[test]
+    fn it_answers_unknown_routes_with_404() {
+        let (status, body) = get("/nowhere");
+
+        assert_eq!(status, Status::NotFound);
+        assert_eq!(body["message"], "nothing at GET /nowhere");
+    }
+
+    #[test]
+    fn it_answers_503_when_postgres_or_rabbitmq_is_unreachable() {
+        let (status, body) = get("/pairs");
+        assert_eq!(status, Status::ServiceUnavailable);
+        assert_eq!(body["status"], 503);
+
+        assert_eq!(get("/depth").0, Status::ServiceUnavailable);
+    }
+
+    #[test]
+    fn it_answers_503_when_rabbitmq_is_not_configured() {
+        let unset = anyhow::Error::from(env::VarError::NotPresent).context("RABBIT_URL is not set");
+
+        let error = ApiError::from(unset);
+        assert_eq!(error.status, Status::ServiceUnavailable);
+        assert_eq!(
+            error.message,
+            "RABBIT_URL is not set: environment variable not found"
+        );
+    }
+}
@@ -1,38 +1,317 @@
-use diesel::PgConnection;
+use std::{
+    any::Any,
+    collections::HashMap,
+    env,
+    panic::{self, AssertUnwindSafe},
+    thread,
+    time::Duration,
+};
+
+use crossbeam_channel::{Receiver, Sender};
+use diesel::connection::TransactionManager;
 use diesel::r2d2::{ConnectionManager, Pool};
+use diesel::{Connection, PgConnection};
 
 use crate::models::Todo;
+use crate::processed_todos;
+use crate::queue::{Consumed, Delivery, WorkQueue};
+use crate::shutdown::Shutdown;
+use crate::todo_domain::TodoDomain;
 use crate::{
     book_todo_handler, ortho_todo_handler, pair_todo_handler, phrase_todo_handler,
     sentence_todo_handler,
 };
 
+// How long the dispatcher waits for a new todo before checking on finished ones again.
+const DISPATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkerConcurrency {
+    // Handler threads, each holding at most one pooled connection at a time.
+    pub threads: usize,
+    // The most todos delivered to this worker but not yet acked. Also the RabbitMQ prefetch.
+    pub prefetch: u16,
+    pub pool_size: u32,
+}
+
+impl WorkerConcurrency {
+    pub fn new(threads: usize) -> WorkerConcurrency {
+        WorkerConcurrency {
+            threads,
+            prefetch: threads as u16,
+            pool_size: threads as u32,
+        }
+    }
+
+    // Prefetch and pool size default to the number of threads.
+    pub fn from_env() -> Result<WorkerConcurrency, anyhow::Error> {
+        let default = WorkerConcurrency::new(match env::var("WORKER_THREADS") {
+            Ok(s) => s.parse()?,
+            Err(_) => 1,
+        });
+        Ok(WorkerConcurrency {
+            prefetch: match env::var("WORKER_PREFETCH") {
+                Ok(s) => s.parse()?,
+                Err(_) => default.prefetch,
+            },
+            pool_size: match env::var("WORKER_POOL_SIZE") {
+                Ok(s) => s.parse()?,
+                Err(_) => default.pool_size,
+            },
+            ..default
+        })
+    }
+}
+
+// Handles todos until the queue stops delivering or shutdown is requested, in which case todos
+// already handed out are finished and acked first. Deliveries are handed to a pool of handler
+// threads, but only this thread touches the queue, so acks and nacks go out on the channel the
+// delivery came in on.
+pub fn work<Q: WorkQueue>(
+    work_queue: &mut Q,
+    pool: Pool<ConnectionManager<PgConnection>>,
+    concurrency: WorkerConcurrency,
+    shutdown: &Shutdown,
+) -> Result<(), anyhow::Error> {
+    work_with(work_queue, concurrency, shutdown, move |todo| {
+        handle_todo(todo, pool.clone())
+    })
+}
+
+// A handler that panics fails its todo like one that returns an error, so the todo is retried or
+// dead-lettered and the handler thread lives on to take the next one.
+fn work_with<Q, H>(
+    work_queue: &mut Q,
+    concurrency: WorkerConcurrency,
+    shutdown: &Shutdown,
+    handle: H,
+) -> Result<(), anyhow::Error>
+where
+    Q: WorkQueue,
+    H: Fn(Todo) -> Result<(), anyhow::Error> + Clone + Send + 'static,
+{
+    let (todo_sender, todo_receiver) = crossbeam_channel::unbounded::<(u64, Todo)>();
+    let (done_sender, done_receiver) = crossbeam_channel::unbounded();
+
+    let handlers: Vec<_> = (0..concurrency.threads.max(1))
+        .map(|_| {
+            let todos = todo_receiver.clone();
+            let done = done_sender.clone();
+            let handle = handle.clone();
+            thread::spawn(move || {
+                for (key, todo) in todos.iter() {
+                    println!("todo: {:?}", &todo);
+                    let result = match panic::catch_unwind(AssertUnwindSafe(|| handle(todo))) {
+                        Ok(result) => result.map_err(|e| e.to_string()),
+                        Err(panic) => Err(format!("handler panicked: {}", panic_message(&*panic))),
+                    };
+                    if done.send((key, result)).is_err() {
+                        break;
+                    }
+                }
+            })
+        })
+        .collect();
+    drop(done_sender);
+
+    let result = dispatch(
+        work_queue,
+        &todo_sender,
+        &done_receiver,
+        usize::from(concurrency.prefetch.max(1)),
+        shutdown,
+    );
+
+    // Unacked deliveries left behind by an error are redelivered by the queue.
+    drop(todo_sender);
+    for handler in handlers {
+        if handler.join().is_err() {
+            println!("a handler thread panicked");
+        }
+    }
+    result
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    match panic.downcast_ref::<&str>() {
+        Some(message) => message,
+        None => panic
+            .downcast_ref::<String>()
+            .map(String::as_str)
+            .unwrap_or("unknown panic"),
+    }
+}
+
+fn dispatch<Q: WorkQueue>(
+    work_queue: &mut Q,
+    todos: &Sender<(u64, Todo)>,
+    done: &Receiver<(u64, Result<(), String>)>,
+    prefetch: usize,
+    shutdown: &Shutdown,
+) -> Result<(), anyhow::Error> {
+    let mut in_flight: HashMap<u64, Delivery<Q::Handle>> = HashMap::new();
+    let mut next_key = 0;
+    let mut ended = false;
+
+    loop {
+        while let Ok((key, result)) = done.try_recv() {
+            settle(work_queue, &mut in_flight, key, result)?;
+        }
+        if shutdown.requested() && !ended {
+            println!("finishing {} todos before shutting down", in_flight.len());
+            ended = true;
+        }
+        if ended && in_flight.is_empty() {
+            return Ok(());
+        }
+        if ended || in_flight.len() >= prefetch {
+            let (key, result) = done.recv()?;
+            settle(work_queue, &mut in_flight, key, result)?;
+            continue;
+        }
+
+        match work_queue.consume_timeout(DISPATCH_INTERVAL)? {
+            Consumed::Delivery(delivery) => {
+                todos.send((next_key, delivery.todo.clone()))?;
+                in_flight.insert(next_key, delivery);
+                next_key += 1;
+            }
+            Consumed::TimedOut => {}
+            Consumed::Ended => ended = true,
+        }
+    }
+}
 
-pub fn handle_todo(todo: Todo, pool: Pool<ConnectionManager<PgConnection>>) -> amiquip::Result<(), anyhow::Error> {
-    let res = match todo.domain.as_str() {
-        "books" => book_todo_handler::handle_book_todo(todo, pool),
-        "sentences" => sentence_todo_handler::handle_sentence_todo(todo, pool),
-        "pairs" => pair_todo_handler::handle_pair_todo(todo, pool),
-        "ex_nihilo_ffbb" => pair_todo_handler::handle_pair_todo_ffbb(todo, pool),
-        "ex_nihilo_fbbf" => pair_todo_handler::handle_pair_todo_fbbf(todo, pool),
-        "pair_up" => pair_todo_handler::handle_pair_todo_up(todo, pool),
-        "up_by_origin" => pair_todo_handler::handle_pair_todo_up_by_origin(todo, pool),
-        "up_by_hop" => pair_todo_handler::handle_pair_todo_up_by_hop(todo, pool),
-        "up_by_contents" => pair_todo_handler::handle_pair_todo_up_by_contents(todo, pool),
-        "orthotopes" => ortho_todo_handler::handle_ortho_todo(todo, pool),
-        "ortho_up" => ortho_todo_handler::handle_ortho_todo_up(todo, pool),
-        "ortho_up_forward" => ortho_todo_handler::handle_ortho_todo_up_forward(todo, pool),
-        "ortho_up_back" => ortho_todo_handler::handle_ortho_todo_up_back(todo, pool),
-        "ortho_over" => ortho_todo_handler::handle_ortho_todo_over(todo, pool),
-        "ortho_over_forward" => ortho_todo_handler::handle_ortho_todo_over_forward(todo, pool),
-        "ortho_over_back" => ortho_todo_handler::handle_ortho_todo_over_back(todo, pool),
-        "phrases" => phrase_todo_handler::handle_phrase_todo(todo, pool),
-        "phrase_by_origin" => phrase_todo_handler::handle_phrase_todo_origin(todo, pool),
-        "phrase_by_hop" => phrase_todo_handler::handle_phrase_todo_hop(todo, pool),
-        "phrase_by_contents" => phrase_todo_handler::handle_phrase_todo_contents(todo, pool),
-        other => {
-            panic!("getting unexpected todo with domain: {other}")
+fn settle<Q: WorkQueue>(
+    work_queue: &mut Q,
+    in_flight: &mut HashMap<u64, Delivery<Q::Handle>>,
+    key: u64,
+    result: Result<(), String>,
+) -> Result<(), anyhow::Error> {
+    let delivery = in_flight
+        .remove(&key)
+        .expect("finished todos are in flight");
+    match result {
+        Ok(()) => work_queue.ack(delivery),
+        Err(e) => {
+            let todo = delivery.todo.clone();
+            let action = work_queue.nack(delivery, &e)?;
+            println!("{:?} for {:?} because of {e}", action, todo);
+            Ok(())
         }
+    }
+}
+
+pub fn handle_todo(
+    todo: Todo,
+    pool: Pool<ConnectionManager<PgConnection>>,
+) -> Result<(), anyhow::Error> {
+    let conn = pool.get()?;
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        conn.build_transaction().serializable().run(|| {
+            if !processed_todos::claim(&conn, &todo)? {
+                return Ok(());
+            }
+            apply_todo(&todo, &conn)
+        })
+    }));
+    match result {
+        Ok(result) => result,
+        Err(panic) => {
+            // The pool does not notice a connection left inside a transaction, so end it here.
+            conn.transaction_manager()
+                .rollback_transaction(&*conn)
+                .expect("the transaction should roll back");
+            panic::resume_unwind(panic)
+        }
+    }
+}
+
+// Runs the handler for a todo inside the caller's transaction.
+pub(crate) fn apply_todo(todo: &Todo, conn: &PgConnection) -> Result<(), anyhow::Error> {
+    match todo.domain {
+        TodoDomain::Books => book_todo_handler::handle_book_todo(todo, conn),
+        TodoDomain::Sentences => sentence_todo_handler::handle_sentence_todo(todo, conn),
+        TodoDomain::Pairs => pair_todo_handler::handle_pair_todo(todo, conn),
+        TodoDomain::ExNihiloFfbb => pair_todo_handler::handle_pair_todo_ffbb(todo, conn),
+        TodoDomain::ExNihiloFbbf => pair_todo_handler::handle_pair_todo_fbbf(todo, conn),
+        TodoDomain::PairUp => pair_todo_handler::handle_pair_todo_up(todo, conn),
+        TodoDomain::UpByOrigin => pair_todo_handler::handle_pair_todo_up_by_origin(todo, conn),
+        TodoDomain::UpByHop => pair_todo_handler::handle_pair_todo_up_by_hop(todo, conn),
+        TodoDomain::UpByContents => pair_todo_handler::handle_pair_todo_up_by_contents(todo, conn),
+        TodoDomain::Orthotopes => ortho_todo_handler::handle_ortho_todo(todo, conn),
+        TodoDomain::OrthoUp => ortho_todo_handler::handle_ortho_todo_up(todo, conn),
+        TodoDomain::OrthoUpForward => ortho_todo_handler::handle_ortho_todo_up_forward(todo, conn),
+        TodoDomain::OrthoUpBack => ortho_todo_handler::handle_ortho_todo_up_back(todo, conn),
+        TodoDomain::OrthoOver => ortho_todo_handler::handle_ortho_todo_over(todo, conn),
+        TodoDomain::OrthoOverForward => {
+            ortho_todo_handler::handle_ortho_todo_over_forward(todo, conn)
+        }
+        TodoDomain::OrthoOverBack => ortho_todo_handler::handle_ortho_todo_over_back(todo, conn),
+        TodoDomain::Phrases => phrase_todo_handler::handle_phrase_todo(todo, conn),
+        TodoDomain::PhraseByOrigin => phrase_todo_handler::handle_phrase_todo_origin(todo, conn),
+        TodoDomain::PhraseByHop => phrase_todo_handler::handle_phrase_todo_hop(todo, conn),
+        TodoDomain::PhraseByContents => {
+            phrase_todo_handler::handle_phrase_todo_contents(todo, conn)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::{
+        in_memory_queue::InMemoryWorkQueue,
+        models::Todo,
+        queue::{RetryPolicy, WorkQueue},
+        shutdown::Shutdown,
+        todo_domain::TodoDomain,
+        worker_helper::{work_with, WorkerConcurrency},
     };
-    res
+
+    fn todo(id: i32) -> Todo {
+        Todo {
+            id,
+            domain: TodoDomain::Pairs,
+            other: id,
+        }
+    }
+
+    #[test]
+    fn a_panicking_handler_fails_its_todo_and_keeps_working() {
+        let mut queue = InMemoryWorkQueue::new(RetryPolicy {
+            max_attempts: 2,
+            base_delay_ms: 0,
+        });
+        queue.publish(&[todo(1), todo(2), todo(3)]).unwrap();
+        queue.close();
+        let handled = Arc::new(Mutex::new(vec![]));
+        let handled_by_worker = handled.clone();
+
+        // One thread, so the todos after the panic are only handled if the thread survives it.
+        work_with(
+            &mut queue,
+            WorkerConcurrency::new(1),
+            &Shutdown::default(),
+            move |todo| {
+                if todo.id == 2 {
+                    panic!("there must be an adjacent name");
+                }
+                handled_by_worker.lock().unwrap().push(todo.id);
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(*handled.lock().unwrap(), vec![1, 3]);
+        let dead = queue.dead_letters();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].todo, Some(todo(2)));
+        assert_eq!(dead[0].attempts, 2);
+        assert_eq!(
+            dead[0].error.as_deref(),
+            Some("handler panicked: there must be an adjacent name")
+        );
+    }
 }
@@ -0,0 +1,49 @@
+use std::{
+    env,
+    process::{self, Stdio},
+};
+
+use polyvinyl_acetate::shutdown::{forced_exit_code, Shutdown, CLEAN_EXIT_CODE};
+use signal_hook::consts::SIGINT;
+
+// Set on the copy of this test binary that `exit_status` runs, to the number of SIGINTs to raise.
+const SIGNALS_VAR: &str = "SHUTDOWN_SIGNAL_TEST_SIGNALS";
+
+// Raises real SIGINTs, so it runs in a test binary of its own. The handlers it registers are
+// process-wide and stay installed, and in the shared unit test binary they would make a later
+// ctrl-c exit the whole run instead of letting it report. Each case runs in a child process
+// because both paths end by exiting.
+#[test]
+fn a_drain_and_a_second_signal_exit_differently() {
+    assert_eq!(exit_status(1), CLEAN_EXIT_CODE);
+    assert_eq!(exit_status(2), forced_exit_code(SIGINT));
+    assert_ne!(CLEAN_EXIT_CODE, forced_exit_code(SIGINT));
+}
+
+// Does nothing unless `exit_status` started this process. Otherwise it shuts down the way the
+// worker and relay binaries do.
+#[test]
+fn signalled_child() {
+    let signals: usize = match env::var(SIGNALS_VAR) {
+        Ok(signals) => signals.parse().unwrap(),
+        Err(_) => return,
+    };
+    let shutdown = Shutdown::register().unwrap();
+    assert!(!shutdown.requested());
+    for _ in 0..signals {
+        signal_hook::low_level::raise(SIGINT).unwrap();
+    }
+    assert!(shutdown.requested());
+    process::exit(CLEAN_EXIT_CODE);
+}
+
+fn exit_status(signals: usize) -> i32 {
+    process::Command::new(env::current_exe().unwrap())
+        .args(["--exact", "signalled_child", "--test-threads", "1"])
+        .env(SIGNALS_VAR, signals.to_string())
+        .stdout(Stdio::null())
+        .status()
+        .unwrap()
+        .code()
+        .unwrap()
+}